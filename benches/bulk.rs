@@ -0,0 +1,69 @@
+// 比较逐个插入与缓存到Vec后批量构建的展开代码
+// 运行: cargo bench --bench bulk
+mod common;
+
+use better_comprehension::{b_tree_map, b_tree_set, binary_heap};
+use common::{bench, report};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+use std::hint::black_box;
+
+fn main() {
+    // 线性同余生成的伪随机数据, 以及已经有序的数据
+    let mut state: u64 = 42;
+    let random: Vec<u64> = (0..10_000)
+        .map(|_| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state >> 33
+        })
+        .collect();
+    let sorted: Vec<u64> = (0..10_000).collect();
+
+    for (label, data) in [("random", &random), ("sorted", &sorted)] {
+        println!("--- {} input ---", label);
+
+        // 之前的展开: 逐个insert
+        let baseline = bench("b_tree_map! insert (previous expansion)", || {
+            let data = black_box(data);
+            let mut map = BTreeMap::new();
+            for x in data.iter() {
+                map.insert(*x, x * 2);
+            }
+            map
+        });
+        let improved = bench("b_tree_map! bulk build", || {
+            let data = black_box(data);
+            b_tree_map![*x => x * 2 for x in data.iter()]
+        });
+        report(baseline, improved);
+
+        let baseline = bench("b_tree_set! insert (previous expansion)", || {
+            let data = black_box(data);
+            let mut set = BTreeSet::new();
+            for x in data.iter() {
+                set.insert(x % 5000);
+            }
+            set
+        });
+        let improved = bench("b_tree_set! bulk build", || {
+            let data = black_box(data);
+            b_tree_set![x % 5000 for x in data.iter()]
+        });
+        report(baseline, improved);
+
+        let baseline = bench("binary_heap! push (previous expansion)", || {
+            let data = black_box(data);
+            let mut heap = BinaryHeap::with_capacity(data.len());
+            for x in data.iter() {
+                heap.push(*x);
+            }
+            heap
+        });
+        let improved = bench("binary_heap! heapify", || {
+            let data = black_box(data);
+            binary_heap![*x for x in data.iter()]
+        });
+        report(baseline, improved);
+    }
+}
@@ -0,0 +1,93 @@
+// 比较预分配容量前后的展开代码
+// 运行: cargo bench --bench capacity
+mod common;
+
+use better_comprehension::{hash_map, vector};
+use common::{bench, report};
+use std::collections::HashMap;
+use std::hint::black_box;
+
+fn main() {
+    let data: Vec<u64> = (0..10_000).collect();
+
+    // 之前的展开: Vec::new() + push
+    let baseline = bench("vector! without capacity (previous expansion)", || {
+        let data = black_box(&data);
+        let mut vec = Vec::new();
+        for x in data.iter() {
+            vec.push((*x, x * 2, x * 3, x * 4));
+        }
+        vec
+    });
+    // 没有其他子句时展开为迭代器管道, 由collect预分配
+    let improved = bench("vector! (iterator pipeline)", || {
+        let data = black_box(&data);
+        vector![(*x, x * 2, x * 3, x * 4) for x in data.iter()]
+    });
+    report(baseline, improved);
+
+    // 有let子句时展开为循环, 由size_hint预留容量
+    let baseline = bench("vector! with let (previous expansion)", || {
+        let data = black_box(&data);
+        let mut vec = Vec::new();
+        for x in data.iter() {
+            let y = x * 2;
+            vec.push((*x, y, x * 3, x * 4));
+        }
+        vec
+    });
+    let improved = bench("vector! with let, size_hint reserve", || {
+        let data = black_box(&data);
+        vector![(*x, y, x * 3, x * 4) for x in data.iter() let y = x * 2]
+    });
+    report(baseline, improved);
+
+    let baseline = bench("filtered vector! without capacity", || {
+        let data = black_box(&data);
+        vector![(*x, x * 2, x * 3, x * 4) for x in data.iter() if x % 3 != 0]
+    });
+    let improved = bench("filtered vector! with capacity", || {
+        let data = black_box(&data);
+        vector![(*x, x * 2, x * 3, x * 4) for x in data.iter() if x % 3 != 0 with capacity data.len()]
+    });
+    report(baseline, improved);
+
+    let baseline = bench("hash_map! without capacity (previous expansion)", || {
+        let data = black_box(&data);
+        let mut map = HashMap::new();
+        for x in data.iter() {
+            map.insert(*x, x * 2);
+        }
+        map
+    });
+    let improved = bench("hash_map! (iterator pipeline)", || {
+        let data = black_box(&data);
+        hash_map![*x => x * 2 for x in data.iter()]
+    });
+    report(baseline, improved);
+
+    let baseline = bench("hash_map! with let (previous expansion)", || {
+        let data = black_box(&data);
+        let mut map = HashMap::new();
+        for x in data.iter() {
+            let y = x * 2;
+            map.insert(*x, y);
+        }
+        map
+    });
+    let improved = bench("hash_map! with let, size_hint reserve", || {
+        let data = black_box(&data);
+        hash_map![*x => y for x in data.iter() let y = x * 2]
+    });
+    report(baseline, improved);
+
+    let baseline = bench("nested hash_map! without capacity", || {
+        let data = black_box(&data[..100]);
+        hash_map![(*x, y) => x * y for x in data.iter() for y in 0..50u64]
+    });
+    let improved = bench("nested hash_map! with capacity", || {
+        let data = black_box(&data[..100]);
+        hash_map![(*x, y) => x * y for x in data.iter() for y in 0..50u64 with capacity data.len() * 50]
+    });
+    report(baseline, improved);
+}
@@ -0,0 +1,40 @@
+// 各个bench共用的计时工具, 通过 `mod common;` 引入
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+// 预热warmup次后, 取samples个样本(每个样本运行iterations次的平均值)的中位数
+pub fn measure<T>(
+    warmup: usize,
+    samples: usize,
+    iterations: u32,
+    mut f: impl FnMut() -> T,
+) -> Duration {
+    for _ in 0..warmup {
+        black_box(f());
+    }
+
+    let mut durations = vec![];
+    for _ in 0..samples {
+        let start = Instant::now();
+        for _ in 0..iterations {
+            black_box(f());
+        }
+        durations.push(start.elapsed() / iterations);
+    }
+    durations.sort();
+    durations[durations.len() / 2]
+}
+
+pub fn bench<T>(name: &str, f: impl FnMut() -> T) -> Duration {
+    let median = measure(10, 50, 100, f);
+    println!("{:<48} {:>12?}", name, median);
+    median
+}
+
+pub fn report(baseline: Duration, improved: Duration) {
+    println!(
+        "{:<48} {:>11.2}x\n",
+        "speedup",
+        baseline.as_secs_f64() / improved.as_secs_f64()
+    );
+}
@@ -0,0 +1,97 @@
+// 比较iterator_ref!之前的展开(每层收集并克隆引用的Vec)与直接遍历借用的集合
+// 运行: cargo bench --bench iterator_ref
+// 之前的展开按原样手写, 因此保留其 filter_map + then 的写法
+#![allow(clippy::filter_map_bool_then)]
+// 分配字节数需要单独统计, 因此只使用共用的measure与report
+#[allow(dead_code)]
+mod common;
+
+use better_comprehension::iterator_ref;
+use common::report;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+// 统计分配的总字节数
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn bench(name: &str, mut f: impl FnMut() -> u64) -> Duration {
+    // 预热
+    for _ in 0..3 {
+        black_box(f());
+    }
+
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    black_box(f());
+    let allocated = ALLOCATED.load(Ordering::Relaxed) - before;
+
+    // 每次调用的开销较大, 每个样本只运行一次
+    let median = common::measure(0, 20, 1, f);
+    println!("{:<48} {:>12?} {:>12} bytes", name, median, allocated);
+    median
+}
+
+fn main() {
+    for size in [10, 100, 300] {
+        println!("--- {} x {} x {} ---", size, size, size);
+        let xs: Vec<u64> = (0..size).collect();
+        let ys: Vec<u64> = (0..size).map(|y| y * 3).collect();
+        let zs: Vec<u64> = (0..size).map(|z| z * 7).collect();
+
+        // 之前的展开: 收集引用到Vec, 并在外层的每次迭代中克隆内层的Vec
+        let baseline = bench("iterator_ref! (previous expansion)", || {
+            let (xs, ys, zs) = black_box((&xs, &ys, &zs));
+            let iter = {
+                let zs = zs.iter().collect::<Vec<_>>();
+                let ys = ys.iter().collect::<Vec<_>>();
+                let xs = xs.iter().collect::<Vec<_>>();
+                (xs).into_iter()
+                    .filter_map(move |x| {
+                        (x % 2 == 0).then(|| {
+                            let zs = zs.clone();
+                            let ys = ys.clone();
+                            (ys).into_iter().filter_map(move |y| {
+                                (true).then(|| {
+                                    let zs = zs.clone();
+                                    (zs).into_iter().filter_map(move |z| {
+                                        ((x + y + z) % 3 == 0).then(|| x ^ y ^ z)
+                                    })
+                                })
+                            })
+                        })
+                    })
+                    .flatten()
+                    .flatten()
+            };
+            iter.fold(0, |acc, v| acc.wrapping_add(v))
+        });
+        let improved = bench("iterator_ref! borrowed iteration", || {
+            let (xs, ys, zs) = black_box((&xs, &ys, &zs));
+            iterator_ref![
+                x ^ y ^ z
+                for x in xs if x % 2 == 0
+                for y in ys
+                for z in zs if (x + y + z) % 3 == 0
+            ]
+            .fold(0, |acc, v| acc.wrapping_add(v))
+        });
+        report(baseline, improved);
+    }
+}
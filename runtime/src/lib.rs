@@ -0,0 +1,134 @@
+/*!
+Runtime companion of [`better_comprehension`](https://crates.io/crates/better_comprehension).
+
+The [`ComprehensionSink`] trait lets any collection be filled with the comprehension syntax
+through the `sink!` macro, and `#[derive(Comprehension)]` generates a dedicated macro for a type.
+All the macros of `better_comprehension` are re-exported, so this is the only dependency needed.
+
+```rust
+use better_comprehension_runtime::{Comprehension, ComprehensionSink};
+
+// a vector that only stores the non-zero values with their indexes
+#[derive(Debug, Default, Comprehension)]
+struct SparseVec {
+    items: Vec<(usize, i32)>,
+}
+
+impl ComprehensionSink for SparseVec {
+    type Item = (usize, i32);
+
+    fn with_capacity_hint(capacity: usize) -> Self {
+        SparseVec { items: Vec::with_capacity(capacity) }
+    }
+
+    fn accept(&mut self, (index, value): (usize, i32)) {
+        if value != 0 {
+            self.items.push((index, value));
+        }
+    }
+}
+
+let dense = vec![0, 3, 0, 0, 7];
+// key-value mappings produce (key, value) items
+let sparse = sparse_vec![i => *x for (i, x) in (dense.iter().enumerate())];
+assert_eq!(sparse.items, vec![(1, 3), (4, 7)]);
+```
+
+`#[comprehension(macro_name = name)]` chooses the macro name (the snake_case type name by default).
+`#[comprehension(path = crate::module::Type)]` chooses the path the macro uses to name the type;
+without it the bare type name is used, so the macro only works where the type is in scope.
+`#[comprehension(export)]` adds `#[macro_export]` to the generated macro and requires `path`.
+
+The std collections do not implement [`ComprehensionSink`],
+they are filled by the dedicated macros of `better_comprehension` (`vector!` `hash_map!` and so on),
+which can use iterator pipelines and bulk construction.
+*/
+
+pub use better_comprehension::*;
+
+// 使生成的 `::better_comprehension_runtime::...` 路径在本crate内也能解析
+extern crate self as better_comprehension_runtime;
+
+/// A collection that can be filled by the `sink!` macro
+pub trait ComprehensionSink {
+    type Item;
+
+    /// Creates an empty collection.
+    /// `capacity` comes from `with capacity n`, otherwise it is `0`
+    fn with_capacity_hint(capacity: usize) -> Self;
+
+    /// Called once before the loop with the lower bound of the iterable's `size_hint`,
+    /// when the number of elements is known (a single `for in` clause without `if`)
+    fn reserve_hint(&mut self, additional: usize) {
+        let _ = additional;
+    }
+
+    /// Adds one element produced by the mapping
+    fn accept(&mut self, item: Self::Item);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Default, PartialEq, Comprehension)]
+    #[comprehension(macro_name = interned)]
+    struct InternedSet<'a> {
+        strings: Vec<&'a str>,
+        reserved: usize,
+    }
+
+    impl<'a> ComprehensionSink for InternedSet<'a> {
+        type Item = &'a str;
+
+        fn with_capacity_hint(capacity: usize) -> Self {
+            InternedSet {
+                strings: vec![],
+                reserved: capacity,
+            }
+        }
+
+        fn reserve_hint(&mut self, additional: usize) {
+            self.reserved += additional;
+        }
+
+        fn accept(&mut self, item: &'a str) {
+            if !self.strings.contains(&item) {
+                self.strings.push(item);
+            }
+        }
+    }
+
+    #[test]
+    fn test_derived_macro() {
+        let words = ["a", "b", "a", "c", "b"];
+        let set = interned![*w for w in words.iter()];
+        assert_eq!(set.strings, vec!["a", "b", "c"]);
+        assert_eq!(set.reserved, words.len());
+
+        // 有if时不会调用reserve_hint
+        let set = interned![*w for w in words.iter() if *w != "c"];
+        assert_eq!(set.strings, vec!["a", "b"]);
+        assert_eq!(set.reserved, 0);
+
+        let set = interned![*w for w in words.iter() if *w != "c" with capacity 8];
+        assert_eq!(set.reserved, 8);
+    }
+
+    #[test]
+    fn test_validate_sink() {
+        // 全部为Ok时得到实现了ComprehensionSink的集合
+        let words = ["a", "b", "a"];
+        let set = validate![InternedSet; Ok::<_, ()>(*w) for w in words.iter()];
+        assert_eq!(set.unwrap().strings, vec!["a", "b"]);
+
+        let inputs = ["1", "x", "3", "y"];
+        let errors = validate![
+            InternedSet with context;
+            x.parse::<i32>().map(|_| *x) for x in inputs.iter()
+        ]
+        .unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].0, &"x");
+    }
+}
@@ -1,142 +1,41 @@
+pub mod asynchronous;
 pub mod b_tree_map;
 pub mod b_tree_set;
 pub mod binary_heap;
+pub mod collect;
+pub mod extend;
+pub mod fallible;
 pub mod hash_map;
 pub mod hash_set;
 pub mod linked_list;
+pub mod parallel;
+pub mod partition;
+pub mod sink;
+pub mod validate;
 pub mod vec_deque;
 pub mod vector;
 
+pub use asynchronous::AsyncComprehension;
 pub use b_tree_map::BTreeMapComprehension;
 pub use b_tree_set::BTreeSetComprehension;
 pub use binary_heap::BinaryHeapComprehension;
+pub use collect::CollectComprehension;
+pub use extend::{ExtendComprehension, ExtendMapComprehension};
+pub use fallible::{OptComprehension, TryComprehension};
 pub use hash_map::HashMapComprehension;
 pub use hash_set::HashSetComprehension;
 pub use linked_list::LinkedListComprehension;
+pub use parallel::ParallelComprehension;
+pub use partition::{PartitionComprehension, UnzipComprehension};
+pub use sink::SinkComprehension;
+pub use validate::ValidateComprehension;
 pub use vec_deque::VecDequeComprehension;
 pub use vector::VecComprehension;
 
-use crate::iter_clause::{BareIfClause, ForInClause, IterClause, LetClause};
-
-use proc_macro2::TokenStream;
-use quote::quote;
-use syn::Expr;
-
-pub(crate) fn handle_nested_loops(
-    iter_clauses: &[IterClause],
-    mut nested_code: TokenStream,
-) -> TokenStream {
-    let mut need_to_shadow: Vec<&Expr> = vec![];
-
-    // 遍历iter_clauses(因为越向后层次越深, 所以直接pop就行了)
-    let mut iter_clauses: Vec<&IterClause> = iter_clauses.iter().collect();
-
-    while let Some(iter_clause) = iter_clauses.pop() {
-        let IterClause {
-            for_in_clause: ForInClause { pat, iterable },
-            if_clause,
-            let_clauses,
-        } = iter_clause;
-
-        let iterable_code = match iterable {
-            Expr::Reference(_) | Expr::Range(_) => quote! { #iterable },
-            Expr::Path(_) => {
-                need_to_shadow.push(iterable);
-                quote! { &#iterable }
-            }
-            Expr::MethodCall(_) => match is_iter(iterable) {
-                true => quote! { #iterable },
-                _ => panic!(
-                    "please ensure the first method call is iter(): \n{:#?}",
-                    iterable
-                ),
-            },
-            Expr::Paren(expr) => {
-                let iterable = &*expr.expr;
-                quote! { #iterable }
-            }
-            _ => panic!("type is not supported: \n{:#?}", iterable),
-        };
-
-        let mut let_clauses: Vec<&LetClause> = let_clauses.iter().collect();
-        while let Some(LetClause { let_expr }) = let_clauses.pop() {
-            nested_code = quote! {
-                #let_expr;
-                #nested_code
-            };
-        }
-
-        // 根据是否有if条件生成循环代码
-        nested_code = match if_clause {
-            Some(BareIfClause { conditions }) => {
-                quote! {
-                    for #pat in #iterable_code {
-                        if #conditions {
-                            #nested_code
-                        }
-                    }
-                }
-            }
-            None => {
-                quote! {
-                    for #pat in #iterable_code {
-                        #nested_code
-                    }
-                }
-            }
-        };
-    }
-
-    // 为需要影子变量的变量添加声明
-    while let Some(shadowed) = need_to_shadow.pop() {
-        nested_code = quote! {
-            let #shadowed = #shadowed;
-            #nested_code
-        };
-    }
-
-    nested_code
-}
-
-struct IterMethodCallFinder {
-    is_iter: bool,
-}
-
-use syn::{ExprMethodCall, visit::Visit};
-impl<'ast> Visit<'ast> for IterMethodCallFinder {
-    fn visit_expr_method_call(&mut self, node: &'ast ExprMethodCall) {
-        match *node.receiver {
-            syn::Expr::Path(_) | syn::Expr::Field(_) => {
-                if node.method == "iter" {
-                    self.is_iter = true;
-                }
-            }
-            _ => syn::visit::visit_expr(&mut *self, &node.receiver),
-        }
-    }
-}
-
-fn is_iter(expr: &syn::Expr) -> bool {
-    let mut finder = IterMethodCallFinder { is_iter: false };
-    finder.visit_expr(expr);
-
-    finder.is_iter
-}
-
-#[test]
-fn test_is_iter() {
-    // 最右侧是iter方法
-    let expr = syn::parse_quote!(some.method_1().method_2().iter());
-    assert!(!is_iter(&expr));
-    eprintln!("--------------------------------");
-    // 最左侧是iter方法
-    let expr = syn::parse_quote!(some.iter().method_3().method_4());
-    assert!(is_iter(&expr));
-    eprintln!("--------------------------------");
-
-    let expr = syn::parse_quote!(some.method_5().iter().method_6());
-    assert!(!is_iter(&expr));
-}
+pub(crate) use better_comprehension_syntax::lowering::{
+    handle_buffer, handle_nested_loops, handle_nested_loops_with_reserve, handle_parallel_loops,
+    handle_pipeline, handle_pipeline_with,
+};
 
 #[cfg(test)]
 mod tests {
@@ -155,6 +54,7 @@ mod tests {
             left_key,
             left_value,
             right_expr,
+            ..
         } = &comprehension.mapping;
         assert!(matches!(left_key, Expr::Binary(_)));
         assert!(right_expr.is_none());
@@ -175,6 +75,7 @@ mod tests {
             left_key,
             left_value,
             right_expr,
+            ..
         } = &comprehension.mapping;
         assert!(matches!(left_key, Expr::Binary(_)));
         assert!(right_expr.is_none());
@@ -198,6 +99,7 @@ mod tests {
             left_key,
             left_value,
             right_expr,
+            ..
         } = &comprehension.mapping;
         assert!(matches!(left_key, Expr::Binary(_)));
         assert!(right_expr.is_some());
@@ -206,6 +108,7 @@ mod tests {
                 conditions,
                 else_key,
                 else_value,
+                ..
             } = mapping_else;
             assert!(matches!(conditions, Expr::Binary(_)));
             assert!(matches!(else_key, Expr::Lit(_)));
@@ -221,6 +124,7 @@ mod tests {
             left_key,
             left_value,
             right_expr,
+            ..
         } = &comprehension.mapping;
         assert!(matches!(left_key, Expr::Binary(_)));
         assert!(right_expr.is_none());
@@ -252,6 +156,7 @@ mod tests {
             left_key,
             left_value,
             right_expr,
+            ..
         } = &comprehension.mapping;
         assert!(matches!(left_key, Expr::Array(_)));
         assert!(right_expr.is_some());
@@ -260,6 +165,7 @@ mod tests {
                 conditions,
                 else_key,
                 else_value,
+                ..
             } = mapping_else;
             assert!(matches!(conditions, Expr::Binary(_)));
             assert!(matches!(else_key, Expr::Tuple(_)));
@@ -289,6 +195,7 @@ mod tests {
             left_key,
             left_value,
             right_expr,
+            ..
         } = &comprehension.mapping;
         assert!(matches!(left_key, Expr::MethodCall(_)));
         assert!(right_expr.is_none());
@@ -0,0 +1,248 @@
+use crate::eager_evaluation::partition::SinkKind;
+use crate::iter_clause::IterClause;
+use crate::mapping::Mapping;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::Expr;
+use syn::parse::ParseStream;
+
+mod keyword {
+    syn::custom_keyword!(concurrently);
+}
+
+/*-----------------AsyncComprehension------------------- */
+// async_xxx![mapping for ...], 展开为async块, 可迭代对象, 条件与let子句中都可以使用await
+// async_xxx![future for ... concurrently(n)], 映射为Future, 最多同时轮询n个
+// 结果按输入的顺序放入集合, 因此与逐个await的结果相同
+#[derive(Debug)]
+pub struct AsyncComprehension {
+    pub sink: SinkKind,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+    pub concurrency: Option<Expr>,
+}
+
+impl quote::ToTokens for AsyncComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let AsyncComprehension {
+            sink,
+            mapping,
+            iter_clauses,
+            concurrency,
+        } = self;
+
+        let is_map = matches!(sink, SinkKind::HashMap | SinkKind::BTreeMap);
+        let collection = format_ident!("__async_comprehension");
+        let new_collection = sink.new_tokens();
+
+        let Some(concurrency) = concurrency else {
+            let item = ok_or_emit!(
+                tokens,
+                match is_map {
+                    true => mapping.pair_tokens(),
+                    false => mapping.value_tokens("async comprehension"),
+                }
+            );
+            let nested_code = ok_or_emit!(
+                tokens,
+                crate::eager_evaluation::handle_nested_loops(
+                    iter_clauses,
+                    sink.insert_tokens(&collection, item),
+                )
+            );
+            tokens.extend(quote! {
+                async {
+                    let mut #collection = #new_collection;
+                    #nested_code
+                    #collection
+                }
+            });
+            return;
+        };
+
+        let left_key = &mapping.left_key;
+        // 键值对的键立即求值, 值为Future(映射与集合是否匹配已在解析时检查)
+        let future = match &mapping.left_value {
+            Some(left_value) => quote! {
+                {
+                    let __async_key = #left_key;
+                    let __async_future = #left_value;
+                    async move { (__async_key, __async_future.await) }
+                }
+            },
+            None => quote! { #left_key },
+        };
+
+        // 轮询所有进行中的Future, 至少一个完成时返回
+        let poll_pending = quote! {
+            ::std::future::poll_fn(|__async_cx| {
+                let mut __async_ready = false;
+                let mut __async_i = 0;
+                while __async_i < __async_pending.len() {
+                    match ::std::future::Future::poll(
+                        ::std::pin::Pin::as_mut(&mut __async_pending[__async_i].1),
+                        __async_cx,
+                    ) {
+                        ::std::task::Poll::Ready(__async_output) => {
+                            let (__async_index, _) = __async_pending.swap_remove(__async_i);
+                            __async_slots[__async_index] =
+                                ::std::option::Option::Some(__async_output);
+                            __async_ready = true;
+                        }
+                        ::std::task::Poll::Pending => __async_i += 1,
+                    }
+                }
+                match __async_ready {
+                    true => ::std::task::Poll::Ready(()),
+                    false => ::std::task::Poll::Pending,
+                }
+            })
+            .await;
+        };
+
+        let nested_code = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_nested_loops(
+                iter_clauses,
+                quote! {
+                    __async_pending.push((__async_slots.len(), ::std::boxed::Box::pin(#future)));
+                    __async_slots.push(::std::option::Option::None);
+                    while __async_pending.len() >= __async_limit {
+                        #poll_pending
+                    }
+                },
+            )
+        );
+        let insert = sink.insert_tokens(&collection, quote! { __async_output });
+
+        tokens.extend(quote! {
+            async {
+                let __async_limit: usize = ::std::cmp::max(#concurrency, 1);
+                let mut __async_slots = ::std::vec::Vec::new();
+                let mut __async_pending = ::std::vec::Vec::new();
+                #nested_code
+                while !__async_pending.is_empty() {
+                    #poll_pending
+                }
+
+                let mut #collection = #new_collection;
+                for __async_slot in __async_slots {
+                    let __async_output = __async_slot.expect("every future has completed");
+                    #insert
+                }
+                #collection
+            }
+        });
+    }
+}
+
+impl AsyncComprehension {
+    pub fn parse_with_sink(input: ParseStream, sink: SinkKind) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+        let concurrency = match input.peek(keyword::concurrently) {
+            true => {
+                input.parse::<keyword::concurrently>()?;
+                let content;
+                syn::parenthesized!(content in input);
+                Some(content.parse::<Expr>()?)
+            }
+            false => None,
+        };
+
+        if let Some(iter_clause) = iter_clauses
+            .iter()
+            .find(|iter_clause| iter_clause.for_in_clause.fallible)
+        {
+            return Err(syn::Error::new_spanned(
+                &iter_clause.for_in_clause.iterable,
+                "`in?` is not supported for async comprehension",
+            ));
+        }
+        if concurrency.is_some() {
+            // 所有的映射必须是同一个Future类型, 因此不支持 `if ... else`
+            if let Some(mapping_else) = &mapping.right_expr {
+                return Err(syn::Error::new_spanned(
+                    &mapping_else.conditions,
+                    "the mapping of concurrent comprehension must be a single future, \
+                    `if ... else` is not supported",
+                ));
+            }
+            let is_map = matches!(sink, SinkKind::HashMap | SinkKind::BTreeMap);
+            match (is_map, &mapping.left_value) {
+                (true, None) => {
+                    return Err(syn::Error::new_spanned(
+                        &mapping.left_key,
+                        "expected a key-value mapping `key => future`",
+                    ));
+                }
+                (false, Some(left_value)) => {
+                    return Err(syn::Error::new_spanned(
+                        left_value,
+                        "the collection doesn't accept key-value mapping",
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            sink,
+            mapping,
+            iter_clauses,
+            concurrency,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse::Parser;
+
+    #[test]
+    fn test_async_parse() {
+        let parse =
+            |input: ParseStream| AsyncComprehension::parse_with_sink(input, SinkKind::Vector);
+        let comprehension = parse
+            .parse2(quote! { fetch(x).await for x in ids.iter() if check(x).await })
+            .unwrap();
+        assert_eq!(comprehension.iter_clauses.len(), 1);
+        assert!(comprehension.concurrency.is_none());
+        eprintln!("AsyncComprehension测试通过");
+
+        let parse =
+            |input: ParseStream| AsyncComprehension::parse_with_sink(input, SinkKind::HashMap);
+        let comprehension = parse
+            .parse2(quote! { *x => fetch(x) for x in ids.iter() concurrently(limit * 2) })
+            .unwrap();
+        assert!(matches!(comprehension.concurrency, Some(Expr::Binary(_))));
+        eprintln!("AsyncComprehension concurrently测试通过");
+
+        // 不支持的映射与子句报告为错误, 而不是panic
+        let error = parse
+            .parse2(
+                quote! { *x => fetch(x) if *x > 0 else skip() for x in ids.iter() concurrently(4) },
+            )
+            .unwrap_err();
+        assert!(error.to_string().contains("single future"));
+        let error = parse
+            .parse2(quote! { fetch(x) for x in ids.iter() concurrently(4) })
+            .unwrap_err();
+        assert!(error.to_string().contains("key-value mapping"));
+        let error = parse
+            .parse2(quote! { fetch(x).await for x in? ids })
+            .unwrap_err();
+        assert!(error.to_string().contains("`in?`"));
+        let parse =
+            |input: ParseStream| AsyncComprehension::parse_with_sink(input, SinkKind::Vector);
+        let error = parse
+            .parse2(quote! { *x => fetch(x) for x in ids.iter() concurrently(4) })
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("doesn't accept key-value mapping")
+        );
+        eprintln!("AsyncComprehension错误测试通过");
+    }
+}
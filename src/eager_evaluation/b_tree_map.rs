@@ -1,5 +1,5 @@
 use crate::iter_clause::IterClause;
-use crate::mapping::{Mapping, MappingElse};
+use crate::mapping::Mapping;
 use quote::quote;
 use syn::parse::ParseStream;
 
@@ -13,49 +13,20 @@ pub struct BTreeMapComprehension {
 impl quote::ToTokens for BTreeMapComprehension {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let BTreeMapComprehension {
-            mapping:
-                Mapping {
-                    left_key,
-                    left_value,
-                    right_expr,
-                },
+            mapping,
             iter_clauses,
         } = self;
 
-        if left_value.is_none() {
-            panic!("missing key-value pair");
-        }
-
-        let mut nested_code = match right_expr {
-            None => quote! {
-                __b_tree_map_comprehension.insert(#left_key, #left_value);
-            },
-            Some(MappingElse {
-                conditions,
-                else_key,
-                else_value,
-            }) => {
-                if else_value.is_none() {
-                    panic!("missing key-value pair");
-                }
-
-                quote! {
-                    if #conditions {
-                        __b_tree_map_comprehension.insert(#left_key, #left_value);
-                    } else {
-                        __b_tree_map_comprehension.insert(#else_key, #else_value);
-                    }
-                }
-            }
-        };
-
-        nested_code = crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code);
-        nested_code = quote! {
+        // 先缓存所有(键, 值), 再通过排序与批量构建创建BTreeMap, 比逐个insert快得多
+        // 稳定排序后相同的键保留最后一个, 与逐个insert的结果一致
+        let pair = ok_or_emit!(tokens, mapping.pair_tokens());
+        let buffer = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_buffer(iter_clauses, pair, None)
+        );
+        let nested_code = quote! {
             {
-                use ::std::collections::BTreeMap;
-                let mut __b_tree_map_comprehension = BTreeMap::new();
-                #nested_code
-                __b_tree_map_comprehension
+                <::std::collections::BTreeMap<_, _> as ::std::iter::FromIterator<_>>::from_iter(#buffer)
             }
         };
 
@@ -65,7 +36,7 @@ impl quote::ToTokens for BTreeMapComprehension {
 
 impl syn::parse::Parse for BTreeMapComprehension {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let (mapping, iter_clauses) = crate::common_parse(input);
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
 
         Ok(Self {
             mapping,
@@ -1,5 +1,5 @@
 use crate::iter_clause::IterClause;
-use crate::mapping::{Mapping, MappingElse};
+use crate::mapping::Mapping;
 use quote::quote;
 use syn::parse::ParseStream;
 
@@ -13,49 +13,25 @@ pub struct BTreeSetComprehension {
 impl quote::ToTokens for BTreeSetComprehension {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let BTreeSetComprehension {
-            mapping:
-                Mapping {
-                    left_key,
-                    left_value,
-                    right_expr,
-                },
+            mapping,
             iter_clauses,
         } = self;
 
-        if left_value.is_some() {
-            panic!("BTreeSet isn't key-value collection");
-        }
-
-        let mut nested_code = match right_expr {
-            None => quote! {
-                __b_tree_set_comprehension.insert(#left_key);
-            },
-            Some(MappingElse {
-                conditions,
-                else_key,
-                else_value,
-            }) => {
-                if else_value.is_some() {
-                    panic!("BTreeSet isn't key-value collection");
-                }
-
-                quote! {
-                    if #conditions {
-                        __b_tree_set_comprehension.insert(#left_key);
-                    } else {
-                        __b_tree_set_comprehension.insert(#else_key);
-                    }
-                }
-            }
-        };
-
-        nested_code = crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code);
-        nested_code = quote! {
+        // 先缓存所有元素, 排序去重后再批量构建BTreeSet
+        // 稳定排序后dedup保留相同元素中的第一个, 与逐个insert的结果一致
+        let value = ok_or_emit!(tokens, mapping.value_tokens("BTreeSet"));
+        let buffer = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_buffer(iter_clauses, value, None)
+        );
+        let nested_code = quote! {
             {
-                use ::std::collections::BTreeSet;
-                let mut __b_tree_set_comprehension = BTreeSet::new();
-                #nested_code
-                __b_tree_set_comprehension
+                let mut __b_tree_set_comprehension: ::std::vec::Vec<_> = #buffer;
+                __b_tree_set_comprehension.sort();
+                __b_tree_set_comprehension.dedup();
+                <::std::collections::BTreeSet<_> as ::std::iter::FromIterator<_>>::from_iter(
+                    __b_tree_set_comprehension,
+                )
             }
         };
 
@@ -65,7 +41,7 @@ impl quote::ToTokens for BTreeSetComprehension {
 
 impl syn::parse::Parse for BTreeSetComprehension {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let (mapping, iter_clauses) = crate::common_parse(input);
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
 
         Ok(Self {
             mapping,
@@ -1,5 +1,6 @@
+use crate::capacity_clause::CapacityClause;
 use crate::iter_clause::IterClause;
-use crate::mapping::{Mapping, MappingElse};
+use crate::mapping::Mapping;
 use quote::quote;
 use syn::parse::ParseStream;
 
@@ -8,50 +9,26 @@ use syn::parse::ParseStream;
 pub struct BinaryHeapComprehension {
     pub mapping: Mapping,
     pub iter_clauses: Vec<IterClause>,
+    pub capacity: Option<CapacityClause>,
 }
 
 impl quote::ToTokens for BinaryHeapComprehension {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let BinaryHeapComprehension {
-            mapping:
-                Mapping {
-                    left_key,
-                    left_value,
-                    right_expr,
-                },
+            mapping,
             iter_clauses,
+            capacity,
         } = self;
 
-        if left_value.is_some() {
-            panic!("BinaryHeap isn't key-value collection");
-        }
-
-        let mut nested_code = match right_expr {
-            None => quote! {
-                __binary_heap_comprehension.push(#left_key);
-            },
-            Some(MappingElse {
-                conditions,
-                else_key,
-                ..
-            }) => {
-                quote! {
-                    if #conditions {
-                        __binary_heap_comprehension.push(#left_key);
-                    } else {
-                        __binary_heap_comprehension.push(#else_key);
-                    }
-                }
-            }
-        };
-
-        nested_code = crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code);
-        nested_code = quote! {
+        // 先缓存所有元素, 再以O(n)的建堆代替n次push
+        let value = ok_or_emit!(tokens, mapping.value_tokens("BinaryHeap"));
+        let buffer = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_buffer(iter_clauses, value, capacity.as_ref())
+        );
+        let nested_code = quote! {
             {
-                use ::std::collections::BinaryHeap;
-                let mut __binary_heap_comprehension = BinaryHeap::new();
-                #nested_code
-                __binary_heap_comprehension
+                ::std::collections::BinaryHeap::from(#buffer)
             }
         };
 
@@ -61,11 +38,13 @@ impl quote::ToTokens for BinaryHeapComprehension {
 
 impl syn::parse::Parse for BinaryHeapComprehension {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let (mapping, iter_clauses) = crate::common_parse(input);
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+        let capacity = crate::capacity_clause::parse_capacity(input)?;
 
         Ok(Self {
             mapping,
             iter_clauses,
+            capacity,
         })
     }
 }
@@ -0,0 +1,222 @@
+use crate::iter_clause::IterClause;
+use crate::mapping::Mapping;
+use quote::quote;
+use syn::Token;
+use syn::parse::ParseStream;
+
+mod keyword {
+    syn::custom_keyword!(default);
+}
+
+/*-----------------CollectComprehension------------------- */
+// collect![Type; mapping for ...], 目标类型只需要实现FromIterator
+// 省略 `Type;` 时目标类型由上下文推断
+// collect![default Type; mapping for ...], 目标类型实现Default + Extend即可(如arena分配的集合)
+#[derive(Debug)]
+pub struct CollectComprehension {
+    pub target: Option<syn::Type>,
+    pub from_default: bool,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for CollectComprehension {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let CollectComprehension {
+            target,
+            from_default,
+            mapping,
+            iter_clauses,
+        } = self;
+
+        // 键值对的映射产生 (键, 值) 元组
+        let item = ok_or_emit!(
+            tokens,
+            match mapping.left_value {
+                Some(_) => mapping.pair_tokens(),
+                None => mapping.value_tokens("collect!"),
+            }
+        );
+
+        // 从默认值开始, 与extend!相同地放入元素
+        if *from_default {
+            let extend = ok_or_emit!(
+                tokens,
+                crate::eager_evaluation::extend::extend_tokens(
+                    &syn::parse_quote! { __collect_comprehension },
+                    item,
+                    iter_clauses,
+                )
+            );
+            tokens.extend(quote! {
+                {
+                    let mut __collect_comprehension =
+                        <#target as ::std::default::Default>::default();
+                    #extend
+                    __collect_comprehension
+                }
+            });
+            return;
+        }
+        let target = match target {
+            Some(target) => quote! { #target },
+            None => quote! { _ },
+        };
+
+        // 简单的形状直接从迭代器管道构建(对Result等类型可以在第一个Err处停止求值)
+        // 否则目标为Result/Option时在循环中遇到第一个Err/None就跳出, 其余情况先缓存到Vec中再构建
+        let nested_code = match crate::eager_evaluation::handle_pipeline(
+            iter_clauses,
+            item.clone(),
+            target.clone(),
+        ) {
+            Some(pipeline) => pipeline,
+            None => match short_circuit(self.target.as_ref()) {
+                Some((wrap_item, stop)) => {
+                    let nested_code = ok_or_emit!(
+                        tokens,
+                        crate::eager_evaluation::handle_nested_loops(
+                            iter_clauses,
+                            quote! {
+                                match #item {
+                                    #wrap_item(__collect_item) => {
+                                        __comprehension_buffer.push(__collect_item);
+                                    }
+                                    #stop => break '__collect_comprehension #stop,
+                                }
+                            },
+                        )
+                    );
+                    quote! {
+                        {
+                            let __collect_comprehension: #target = '__collect_comprehension: {
+                                let mut __comprehension_buffer = ::std::vec::Vec::new();
+                                #nested_code
+                                #wrap_item(::std::iter::FromIterator::from_iter(
+                                    __comprehension_buffer,
+                                ))
+                            };
+                            __collect_comprehension
+                        }
+                    }
+                }
+                None => {
+                    let buffer = ok_or_emit!(
+                        tokens,
+                        crate::eager_evaluation::handle_buffer(iter_clauses, item, None)
+                    );
+                    quote! {
+                        <#target as ::std::iter::FromIterator<_>>::from_iter(#buffer)
+                    }
+                }
+            },
+        };
+
+        tokens.extend(nested_code);
+    }
+}
+
+// 目标类型为Result或Option时, 返回(包装元素的变体, 停止求值的模式)
+// 与标准库中它们的FromIterator相同, 在第一个Err/None处停止
+fn short_circuit(
+    target: Option<&syn::Type>,
+) -> Option<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let Some(syn::Type::Path(type_path)) = target else {
+        return None;
+    };
+    match type_path.path.segments.last()?.ident.to_string().as_str() {
+        "Result" => Some((
+            quote! { ::std::result::Result::Ok },
+            quote! { ::std::result::Result::Err(__collect_error) },
+        )),
+        "Option" => Some((
+            quote! { ::std::option::Option::Some },
+            quote! { ::std::option::Option::None },
+        )),
+        _ => None,
+    }
+}
+
+// 解析可选的 `Type;` 前缀
+pub(crate) fn parse_target(input: ParseStream) -> syn::Result<Option<syn::Type>> {
+    let fork = input.fork();
+    match fork.parse::<syn::Type>() {
+        Ok(_) if fork.peek(Token![;]) => {
+            let target = input.parse::<syn::Type>()?;
+            input.parse::<Token![;]>()?;
+            Ok(Some(target))
+        }
+        _ => Ok(None),
+    }
+}
+
+impl syn::parse::Parse for CollectComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        // 只有 `default Type;` 被视为开关, 名为default的变量仍然可以作为映射
+        let fork = input.fork();
+        let from_default =
+            fork.parse::<keyword::default>().is_ok() && parse_target(&fork)?.is_some();
+        if from_default {
+            input.parse::<keyword::default>()?;
+        }
+        let target = parse_target(input)?;
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            target,
+            from_default,
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_collect_parse() {
+        // 显式给出目标类型
+        let collect: CollectComprehension = parse_quote! {
+            Result<Vec<_>, String>; x.parse::<i32>() for x in items
+        };
+        assert!(matches!(collect.target, Some(syn::Type::Path(_))));
+        assert_eq!(collect.iter_clauses.len(), 1);
+        eprintln!("CollectComprehension显式类型测试通过");
+
+        // 由上下文推断目标类型
+        let collect: CollectComprehension = parse_quote! {
+            x => y for x in items for y in x.iter()
+        };
+        assert!(collect.target.is_none());
+        assert!(collect.mapping.left_value.is_some());
+        assert_eq!(collect.iter_clauses.len(), 2);
+        eprintln!("CollectComprehension推断类型测试通过");
+
+        // 从Default::default()开始, 通过Extend放入
+        let collect: CollectComprehension = parse_quote! {
+            default ArenaVec<i32>; x * 2 for x in items
+        };
+        assert!(collect.from_default);
+        assert!(matches!(collect.target, Some(syn::Type::Path(_))));
+        let collect: CollectComprehension = parse_quote! {
+            default + 1 for default in items
+        };
+        assert!(!collect.from_default);
+        eprintln!("CollectComprehension default测试通过");
+
+        // 目标为Result/Option时在循环中短路
+        let collect: CollectComprehension = parse_quote! {
+            Result<Vec<_>, String>; y for x in items let y = x.parse()
+        };
+        let tokens = quote! { #collect }.to_string();
+        assert!(tokens.contains("break '__collect_comprehension"));
+        assert!(short_circuit(Some(&parse_quote! { io::Result<Vec<u8>> })).is_some());
+        assert!(short_circuit(Some(&parse_quote! { Option<String> })).is_some());
+        assert!(short_circuit(Some(&parse_quote! { Vec<Result<i32, ()>> })).is_none());
+        assert!(short_circuit(None).is_none());
+        eprintln!("CollectComprehension短路测试通过");
+    }
+}
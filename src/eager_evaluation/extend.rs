@@ -0,0 +1,113 @@
+use crate::iter_clause::IterClause;
+use crate::mapping::Mapping;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Expr;
+use syn::Token;
+use syn::parse::ParseStream;
+
+// 目标是一个位置表达式(如 `v`, `self.items`, `*r`), 只求值一次
+// 元素通过目标的Extend实现放入, 与各个推导式的push/insert/push_back一致
+// 简单的形状整体交给一次extend, 由集合根据size_hint预留容量
+pub(crate) fn extend_tokens(
+    target: &Expr,
+    item: TokenStream,
+    iter_clauses: &[IterClause],
+) -> syn::Result<TokenStream> {
+    let extend = quote! { ::std::iter::Extend::extend };
+
+    let nested_code = match crate::eager_evaluation::handle_pipeline_with(
+        iter_clauses,
+        item.clone(),
+        |pipeline| {
+            quote! { #extend(__extend_target, #pipeline) }
+        },
+    ) {
+        Some(pipeline) => pipeline,
+        None => crate::eager_evaluation::handle_nested_loops(
+            iter_clauses,
+            quote! {
+                #extend(__extend_target, ::std::option::Option::Some(#item));
+            },
+        )?,
+    };
+
+    Ok(quote! {
+        {
+            let __extend_target = &mut #target;
+            #nested_code;
+        }
+    })
+}
+
+fn parse_target(input: ParseStream) -> syn::Result<Expr> {
+    let target = input.parse::<Expr>()?;
+    input.parse::<Token![;]>()?;
+    Ok(target)
+}
+
+/*-----------------ExtendComprehension------------------- */
+// extend![target; mapping for ...]
+#[derive(Debug)]
+pub struct ExtendComprehension {
+    pub target: Expr,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for ExtendComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let item = ok_or_emit!(tokens, self.mapping.value_tokens("extend!"));
+        let nested_code = ok_or_emit!(
+            tokens,
+            extend_tokens(&self.target, item, &self.iter_clauses)
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for ExtendComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let target = parse_target(input)?;
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            target,
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+/*-----------------ExtendMapComprehension------------------- */
+// extend_map![target; key => value for ...]
+#[derive(Debug)]
+pub struct ExtendMapComprehension {
+    pub target: Expr,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for ExtendMapComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let item = ok_or_emit!(tokens, self.mapping.pair_tokens());
+        let nested_code = ok_or_emit!(
+            tokens,
+            extend_tokens(&self.target, item, &self.iter_clauses)
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for ExtendMapComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let target = parse_target(input)?;
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            target,
+            mapping,
+            iter_clauses,
+        })
+    }
+}
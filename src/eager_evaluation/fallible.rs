@@ -0,0 +1,72 @@
+use quote::quote;
+use syn::parse::ParseStream;
+
+/*-----------------TryComprehension------------------- */
+// try_xxx![...], 将推导式包装在立即调用的闭包中
+// 映射, 条件, let子句与 `in?` 中的`?`都只会从推导式中返回, 结果为 Result<集合, E>
+// 错误经由From转换为E, E由上下文推断
+// 闭包同样改变了 return, 指向宏外的 break/continue 与 .await 的含义(.await 无法编译), 见README
+#[derive(Debug)]
+pub struct TryComprehension<T> {
+    pub comprehension: T,
+}
+
+impl<T> quote::ToTokens for TryComprehension<T>
+where
+    T: quote::ToTokens,
+{
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let comprehension = &self.comprehension;
+
+        tokens.extend(quote! {
+            (|| -> ::std::result::Result<_, _> {
+                ::std::result::Result::Ok(#comprehension)
+            })()
+        });
+    }
+}
+
+impl<T> syn::parse::Parse for TryComprehension<T>
+where
+    T: syn::parse::Parse,
+{
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(Self {
+            comprehension: input.parse::<T>()?,
+        })
+    }
+}
+
+/*-----------------OptComprehension------------------- */
+// opt_xxx![...], 与try_xxx!相同, 但`?`作用于Option, 结果为 Option<集合>
+// 任何一个`?`遇到None时整个推导式为None; 需要跳过元素时仍然使用 `if let`
+#[derive(Debug)]
+pub struct OptComprehension<T> {
+    pub comprehension: T,
+}
+
+impl<T> quote::ToTokens for OptComprehension<T>
+where
+    T: quote::ToTokens,
+{
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let comprehension = &self.comprehension;
+
+        tokens.extend(quote! {
+            (|| -> ::std::option::Option<_> {
+                ::std::option::Option::Some(#comprehension)
+            })()
+        });
+    }
+}
+
+impl<T> syn::parse::Parse for OptComprehension<T>
+where
+    T: syn::parse::Parse,
+{
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(Self {
+            comprehension: input.parse::<T>()?,
+        })
+    }
+}
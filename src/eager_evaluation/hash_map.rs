@@ -1,3 +1,4 @@
+use crate::capacity_clause::CapacityClause;
 use crate::iter_clause::IterClause;
 use crate::mapping::{Mapping, MappingElse};
 use quote::quote;
@@ -8,6 +9,7 @@ use syn::parse::ParseStream;
 pub struct HashMapComprehension {
     pub mapping: Mapping,
     pub iter_clauses: Vec<IterClause>,
+    pub capacity: Option<CapacityClause>,
 }
 
 impl quote::ToTokens for HashMapComprehension {
@@ -18,12 +20,24 @@ impl quote::ToTokens for HashMapComprehension {
                     left_key,
                     left_value,
                     right_expr,
+                    ..
                 },
             iter_clauses,
+            capacity,
         } = self;
 
-        if left_value.is_none() {
-            panic!("missing key-value pair");
+        let pair = ok_or_emit!(tokens, self.mapping.pair_tokens());
+
+        // 简单的形状直接生成迭代器管道
+        if capacity.is_none()
+            && let Some(pipeline) = crate::eager_evaluation::handle_pipeline(
+                iter_clauses,
+                pair,
+                quote! { ::std::collections::HashMap<_, _> },
+            )
+        {
+            tokens.extend(pipeline);
+            return;
         }
 
         let mut nested_code = match right_expr {
@@ -34,11 +48,8 @@ impl quote::ToTokens for HashMapComprehension {
                 conditions,
                 else_key,
                 else_value,
+                ..
             }) => {
-                if else_value.is_none() {
-                    panic!("missing key-value pair");
-                }
-
                 quote! {
                     if #conditions {
                         __hash_map_comprehension.insert(#left_key, #left_value);
@@ -49,11 +60,23 @@ impl quote::ToTokens for HashMapComprehension {
             }
         };
 
-        nested_code = crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code);
+        let (new_collection, reserve) = crate::capacity_clause::handle_capacity(
+            capacity.as_ref(),
+            quote! { HashMap },
+            quote! { __hash_map_comprehension },
+        );
+        nested_code = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_nested_loops_with_reserve(
+                iter_clauses,
+                nested_code,
+                reserve,
+            )
+        );
         nested_code = quote! {
             {
                 use ::std::collections::HashMap;
-                let mut __hash_map_comprehension = HashMap::new();
+                let mut __hash_map_comprehension = #new_collection;
                 #nested_code
                 __hash_map_comprehension
             }
@@ -65,11 +88,13 @@ impl quote::ToTokens for HashMapComprehension {
 
 impl syn::parse::Parse for HashMapComprehension {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let (mapping, iter_clauses) = crate::common_parse(input);
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+        let capacity = crate::capacity_clause::parse_capacity(input)?;
 
         Ok(Self {
             mapping,
             iter_clauses,
+            capacity,
         })
     }
 }
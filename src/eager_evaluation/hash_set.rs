@@ -1,3 +1,4 @@
+use crate::capacity_clause::CapacityClause;
 use crate::iter_clause::IterClause;
 use crate::mapping::{Mapping, MappingElse};
 use quote::quote;
@@ -8,6 +9,7 @@ use syn::parse::ParseStream;
 pub struct HashSetComprehension {
     pub mapping: Mapping,
     pub iter_clauses: Vec<IterClause>,
+    pub capacity: Option<CapacityClause>,
 }
 
 impl quote::ToTokens for HashSetComprehension {
@@ -16,14 +18,25 @@ impl quote::ToTokens for HashSetComprehension {
             mapping:
                 Mapping {
                     left_key,
-                    left_value,
                     right_expr,
+                    ..
                 },
             iter_clauses,
+            capacity,
         } = self;
 
-        if left_value.is_some() {
-            panic!("HashSet isn't key-value collection");
+        let value = ok_or_emit!(tokens, self.mapping.value_tokens("HashSet"));
+
+        // 简单的形状直接生成迭代器管道
+        if capacity.is_none()
+            && let Some(pipeline) = crate::eager_evaluation::handle_pipeline(
+                iter_clauses,
+                value,
+                quote! { ::std::collections::HashSet<_> },
+            )
+        {
+            tokens.extend(pipeline);
+            return;
         }
 
         let mut nested_code = match right_expr {
@@ -33,12 +46,8 @@ impl quote::ToTokens for HashSetComprehension {
             Some(MappingElse {
                 conditions,
                 else_key,
-                else_value,
+                ..
             }) => {
-                if else_value.is_some() {
-                    panic!("HashSet isn't key-value collection");
-                }
-
                 quote! {
                     if #conditions {
                         __hash_set_comprehension.insert(#left_key);
@@ -49,11 +58,23 @@ impl quote::ToTokens for HashSetComprehension {
             }
         };
 
-        nested_code = crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code);
+        let (new_collection, reserve) = crate::capacity_clause::handle_capacity(
+            capacity.as_ref(),
+            quote! { HashSet },
+            quote! { __hash_set_comprehension },
+        );
+        nested_code = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_nested_loops_with_reserve(
+                iter_clauses,
+                nested_code,
+                reserve,
+            )
+        );
         nested_code = quote! {
             {
                 use ::std::collections::HashSet;
-                let mut __hash_set_comprehension = HashSet::new();
+                let mut __hash_set_comprehension = #new_collection;
                 #nested_code
                 __hash_set_comprehension
             }
@@ -65,11 +86,13 @@ impl quote::ToTokens for HashSetComprehension {
 
 impl syn::parse::Parse for HashSetComprehension {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let (mapping, iter_clauses) = crate::common_parse(input);
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+        let capacity = crate::capacity_clause::parse_capacity(input)?;
 
         Ok(Self {
             mapping,
             iter_clauses,
+            capacity,
         })
     }
 }
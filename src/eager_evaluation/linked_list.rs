@@ -1,5 +1,6 @@
 use crate::iter_clause::IterClause;
-use crate::mapping::{Mapping, MappingElse};
+use crate::mapping::Mapping;
+use crate::sequence_clause::{DistinctClause, OrderByClause, TakeClause};
 use quote::quote;
 use syn::parse::ParseStream;
 
@@ -8,48 +9,48 @@ use syn::parse::ParseStream;
 pub struct LinkedListComprehension {
     pub mapping: Mapping,
     pub iter_clauses: Vec<IterClause>,
+    pub distinct: Option<DistinctClause>,
+    pub order_by: Option<OrderByClause>,
+    pub take: Option<TakeClause>,
 }
 
 impl quote::ToTokens for LinkedListComprehension {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let LinkedListComprehension {
-            mapping:
-                Mapping {
-                    left_key,
-                    left_value,
-                    right_expr,
-                },
+            mapping,
             iter_clauses,
+            distinct,
+            order_by,
+            take,
         } = self;
 
-        if left_value.is_some() {
-            panic!("LinkedList isn't key-value collection");
+        // 简单的形状直接生成迭代器管道
+        if distinct.is_none()
+            && order_by.is_none()
+            && take.is_none()
+            && let Some(pipeline) = crate::eager_evaluation::handle_pipeline(
+                iter_clauses,
+                ok_or_emit!(tokens, mapping.value_tokens("LinkedList")),
+                quote! { ::std::collections::LinkedList<_> },
+            )
+        {
+            tokens.extend(pipeline);
+            return;
         }
 
-        let mut nested_code = match right_expr {
-            None => quote! {
-                __linked_list_comprehension.push_back(#left_key);
-            },
-            Some(MappingElse {
-                conditions,
-                else_key,
-                else_value,
-            }) => {
-                if else_value.is_some() {
-                    panic!("LinkedList isn't key-value collection");
-                }
-
-                quote! {
-                    if #conditions {
-                        __linked_list_comprehension.push_back(#left_key);
-                    } else {
-                        __linked_list_comprehension.push_back(#else_key);
-                    }
-                }
-            }
-        };
-
-        nested_code = crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code);
+        let value = ok_or_emit!(tokens, mapping.value_tokens("LinkedList"));
+        let mut nested_code = ok_or_emit!(
+            tokens,
+            crate::sequence_clause::handle_sequence_clauses(
+                iter_clauses,
+                distinct.as_ref(),
+                order_by.as_ref(),
+                take.as_ref(),
+                None,
+                value,
+                |item| quote! { __linked_list_comprehension.push_back(#item); },
+            )
+        );
         nested_code = quote! {
             {
                 use ::std::collections::LinkedList;
@@ -65,11 +66,16 @@ impl quote::ToTokens for LinkedListComprehension {
 
 impl syn::parse::Parse for LinkedListComprehension {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let (mapping, iter_clauses) = crate::common_parse(input);
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+        let distinct = crate::sequence_clause::parse_distinct(input)?;
+        let (order_by, take) = crate::sequence_clause::parse_order_by_take(input)?;
 
         Ok(Self {
             mapping,
             iter_clauses,
+            distinct,
+            order_by,
+            take,
         })
     }
 }
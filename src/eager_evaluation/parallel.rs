@@ -0,0 +1,228 @@
+use crate::eager_evaluation::partition::SinkKind;
+use crate::iter_clause::IterClause;
+use crate::mapping::Mapping;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::Expr;
+use syn::parse::ParseStream;
+
+mod keyword {
+    syn::custom_keyword!(with);
+    syn::custom_keyword!(threads);
+    syn::custom_keyword!(chunk);
+}
+
+/*-----------------ParallelComprehension------------------- */
+// par_xxx![mapping for ... with threads n with chunk m]
+// 最外层的可迭代对象被分为大小为m的块, 由n个线程(std::thread::scope)依次领取
+// 每个块产生一个局部集合, 最后按块的顺序用Extend合并, 因此结果与顺序执行的推导式相同
+#[derive(Debug)]
+pub struct ParallelComprehension {
+    pub sink: SinkKind,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+    pub threads: Option<Expr>,
+    pub chunk: Option<Expr>,
+}
+
+impl quote::ToTokens for ParallelComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let ParallelComprehension {
+            sink,
+            mapping,
+            iter_clauses,
+            threads,
+            chunk,
+        } = self;
+
+        let item = ok_or_emit!(
+            tokens,
+            match sink {
+                SinkKind::HashMap | SinkKind::BTreeMap => mapping.pair_tokens(),
+                _ => mapping.value_tokens("parallel comprehension"),
+            }
+        );
+        let collection = format_ident!("__par_comprehension");
+        let insert = sink.insert_tokens(&collection, item);
+        let new_collection = sink.new_tokens();
+
+        let (prelude, outer_iterable, loops) = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_parallel_loops(
+                iter_clauses,
+                quote! { __par_chunk },
+                insert,
+            )
+        );
+
+        let threads = match threads {
+            Some(threads) => quote! { #threads },
+            None => quote! {
+                ::std::thread::available_parallelism().map_or(1, ::std::num::NonZeroUsize::get)
+            },
+        };
+        // 默认每个线程约领取4个块, 以平衡各层嵌套循环不均匀的负载
+        let chunk = match chunk {
+            Some(chunk) => quote! { #chunk },
+            None => quote! { __par_items.len().div_ceil(__par_threads * 4) },
+        };
+
+        tokens.extend(quote! {
+            {
+                #prelude
+                let __par_items: ::std::vec::Vec<_> = ::std::iter::Iterator::collect(
+                    ::std::iter::IntoIterator::into_iter(#outer_iterable),
+                );
+                let __par_threads: usize = ::std::cmp::max(#threads, 1);
+                let __par_chunk_size: usize = ::std::cmp::max(#chunk, 1);
+
+                let mut __par_items = ::std::iter::IntoIterator::into_iter(__par_items);
+                let mut __par_chunks = ::std::vec::Vec::new();
+                loop {
+                    let __par_chunk: ::std::vec::Vec<_> = ::std::iter::Iterator::collect(
+                        ::std::iter::Iterator::take(&mut __par_items, __par_chunk_size),
+                    );
+                    if __par_chunk.is_empty() {
+                        break;
+                    }
+                    __par_chunks.push(__par_chunk);
+                }
+                let __par_workers = ::std::cmp::min(__par_threads, __par_chunks.len());
+                let __par_queue = ::std::sync::Mutex::new(::std::iter::Iterator::enumerate(
+                    ::std::iter::IntoIterator::into_iter(__par_chunks),
+                ));
+
+                let mut __par_parts = ::std::thread::scope(|__par_scope| {
+                    let __par_handles: ::std::vec::Vec<_> = (0..__par_workers)
+                        .map(|_| {
+                            __par_scope.spawn(|| {
+                                let mut __par_parts = ::std::vec::Vec::new();
+                                loop {
+                                    let __par_next = __par_queue
+                                        .lock()
+                                        .unwrap_or_else(::std::sync::PoisonError::into_inner)
+                                        .next();
+                                    let ::std::option::Option::Some((__par_index, __par_chunk)) =
+                                        __par_next
+                                    else {
+                                        break;
+                                    };
+                                    let mut #collection = #new_collection;
+                                    #loops
+                                    __par_parts.push((__par_index, #collection));
+                                }
+                                __par_parts
+                            })
+                        })
+                        .collect();
+
+                    let mut __par_parts = ::std::vec::Vec::new();
+                    for __par_handle in __par_handles {
+                        match __par_handle.join() {
+                            ::std::result::Result::Ok(__par_part) => __par_parts.extend(__par_part),
+                            ::std::result::Result::Err(__par_panic) => {
+                                ::std::panic::resume_unwind(__par_panic)
+                            }
+                        }
+                    }
+                    __par_parts
+                });
+
+                // 按块的顺序合并, 序列按输入的顺序排列, 集合与映射取并集(后出现的键覆盖先出现的)
+                __par_parts.sort_by_key(|(__par_index, _)| *__par_index);
+                let mut __par_result = #new_collection;
+                for (_, __par_part) in __par_parts {
+                    ::std::iter::Extend::extend(&mut __par_result, __par_part);
+                }
+                __par_result
+            }
+        });
+    }
+}
+
+impl ParallelComprehension {
+    pub fn parse_with_sink(input: ParseStream, sink: SinkKind) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+        if iter_clauses.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &mapping.left_key,
+                "parallel comprehension needs at least one `for` clause",
+            ));
+        }
+        if let Some(iter_clause) = iter_clauses
+            .iter()
+            .find(|iter_clause| iter_clause.for_in_clause.fallible)
+        {
+            return Err(syn::Error::new_spanned(
+                &iter_clause.for_in_clause.iterable,
+                "`in?` is not supported for parallel comprehension",
+            ));
+        }
+
+        let mut threads = None;
+        let mut chunk = None;
+        while input.peek(keyword::with) {
+            input.parse::<keyword::with>()?;
+            if input.peek(keyword::threads) {
+                input.parse::<keyword::threads>()?;
+                threads = Some(input.parse::<Expr>()?);
+            } else {
+                input.parse::<keyword::chunk>()?;
+                chunk = Some(input.parse::<Expr>()?);
+            }
+        }
+
+        Ok(Self {
+            sink,
+            mapping,
+            iter_clauses,
+            threads,
+            chunk,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse::Parser;
+
+    #[test]
+    fn test_parallel_parse() {
+        let parse =
+            |input: ParseStream| ParallelComprehension::parse_with_sink(input, SinkKind::Vector);
+        let parallel = parse
+            .parse2(quote! { x * 2 for x in items if x > 0 with threads 4 with chunk n / 8 })
+            .unwrap();
+        assert_eq!(parallel.iter_clauses.len(), 1);
+        assert!(matches!(parallel.threads, Some(Expr::Lit(_))));
+        assert!(matches!(parallel.chunk, Some(Expr::Binary(_))));
+        eprintln!("ParallelComprehension测试通过");
+
+        let parse =
+            |input: ParseStream| ParallelComprehension::parse_with_sink(input, SinkKind::HashMap);
+        let parallel = parse
+            .parse2(quote! { x => y for x in xs for y in ys with chunk 16 })
+            .unwrap();
+        assert_eq!(parallel.iter_clauses.len(), 2);
+        assert!(parallel.threads.is_none());
+        assert!(parallel.chunk.is_some());
+        eprintln!("ParallelComprehension键值对测试通过");
+
+        let parse =
+            |input: ParseStream| ParallelComprehension::parse_with_sink(input, SinkKind::Vector);
+        assert!(
+            parse
+                .parse2(quote! { x for x in xs with workers 4 })
+                .is_err()
+        );
+        eprintln!("未知配置测试通过");
+
+        // 不支持的子句报告为错误, 而不是panic
+        let error = parse.parse2(quote! { x for x in? xs }).unwrap_err();
+        assert!(error.to_string().contains("`in?`"));
+        let error = parse.parse2(quote! { x }).unwrap_err();
+        assert!(error.to_string().contains("at least one `for` clause"));
+        eprintln!("不支持的子句测试通过");
+    }
+}
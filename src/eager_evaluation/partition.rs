@@ -0,0 +1,291 @@
+use crate::iter_clause::IterClause;
+use crate::mapping::{Mapping, MappingElse};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::Token;
+use syn::parse::ParseStream;
+use syn::punctuated::Punctuated;
+
+/*-----------------SinkKind------------------- */
+// 多输出推导式的目标集合, 以对应的宏名表示: (vector, hash_map, ...)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkKind {
+    Vector,
+    VecDeque,
+    LinkedList,
+    BinaryHeap,
+    HashSet,
+    BTreeSet,
+    HashMap,
+    BTreeMap,
+}
+
+impl syn::parse::Parse for SinkKind {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident = input.parse::<syn::Ident>()?;
+        match ident.to_string().as_str() {
+            "vector" => Ok(Self::Vector),
+            "vec_deque" => Ok(Self::VecDeque),
+            "linked_list" => Ok(Self::LinkedList),
+            "binary_heap" => Ok(Self::BinaryHeap),
+            "hash_set" => Ok(Self::HashSet),
+            "b_tree_set" => Ok(Self::BTreeSet),
+            "hash_map" => Ok(Self::HashMap),
+            "b_tree_map" => Ok(Self::BTreeMap),
+            _ => Err(syn::Error::new(
+                ident.span(),
+                "expected one of: vector, vec_deque, linked_list, binary_heap, \
+                 hash_set, b_tree_set, hash_map, b_tree_map",
+            )),
+        }
+    }
+}
+
+impl SinkKind {
+    pub(crate) fn new_tokens(self) -> TokenStream {
+        match self {
+            Self::Vector => quote! { ::std::vec::Vec::new() },
+            Self::VecDeque => quote! { ::std::collections::VecDeque::new() },
+            Self::LinkedList => quote! { ::std::collections::LinkedList::new() },
+            Self::BinaryHeap => quote! { ::std::collections::BinaryHeap::new() },
+            Self::HashSet => quote! { ::std::collections::HashSet::new() },
+            Self::BTreeSet => quote! { ::std::collections::BTreeSet::new() },
+            Self::HashMap => quote! { ::std::collections::HashMap::new() },
+            Self::BTreeMap => quote! { ::std::collections::BTreeMap::new() },
+        }
+    }
+
+    // 与各个推导式相同的插入方式, 键值对集合的元素为 (键, 值) 元组
+    pub(crate) fn insert_tokens(self, sink: &syn::Ident, item: TokenStream) -> TokenStream {
+        match self {
+            Self::Vector | Self::BinaryHeap => quote! { #sink.push(#item); },
+            Self::VecDeque | Self::LinkedList => quote! { #sink.push_back(#item); },
+            Self::HashSet | Self::BTreeSet => quote! { #sink.insert(#item); },
+            Self::HashMap | Self::BTreeMap => quote! {
+                let (__multi_key, __multi_value) = #item;
+                #sink.insert(__multi_key, __multi_value);
+            },
+        }
+    }
+}
+
+// 解析 `(kind, kind, ...);`
+fn parse_sinks(input: ParseStream) -> syn::Result<Vec<SinkKind>> {
+    let content;
+    syn::parenthesized!(content in input);
+    let sinks = Punctuated::<SinkKind, Token![,]>::parse_terminated(&content)?;
+    input.parse::<Token![;]>()?;
+    Ok(sinks.into_iter().collect())
+}
+
+// 映射中的一个分支: 有值时为 (键, 值) 元组
+fn branch_tokens(key: &syn::Expr, value: Option<&syn::Expr>) -> TokenStream {
+    match value {
+        Some(value) => quote! { (#key, #value) },
+        None => quote! { #key },
+    }
+}
+
+fn multi_tokens(
+    sinks: &[SinkKind],
+    iter_clauses: &[IterClause],
+    inner_code: impl FnOnce(&[syn::Ident]) -> TokenStream,
+) -> syn::Result<TokenStream> {
+    let names: Vec<syn::Ident> = (0..sinks.len())
+        .map(|i| format_ident!("__multi_comprehension_{}", i))
+        .collect();
+    let declares = sinks.iter().zip(&names).map(|(sink, name)| {
+        let new_sink = sink.new_tokens();
+        quote! { let mut #name = #new_sink; }
+    });
+
+    let nested_code =
+        crate::eager_evaluation::handle_nested_loops(iter_clauses, inner_code(&names))?;
+
+    Ok(quote! {
+        {
+            #(#declares)*
+            #nested_code
+            ( #(#names,)* )
+        }
+    })
+}
+
+/*-----------------PartitionComprehension------------------- */
+// partition![(vector, vector); x if pred else y for ...]
+// 条件成立时放入第一个集合, 否则放入第二个集合
+#[derive(Debug)]
+pub struct PartitionComprehension {
+    pub sinks: Vec<SinkKind>,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for PartitionComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let PartitionComprehension {
+            sinks,
+            mapping,
+            iter_clauses,
+        } = self;
+
+        // 集合的个数与映射的形式已在解析时检查
+        let Some(MappingElse {
+            conditions,
+            else_key,
+            else_value,
+            ..
+        }) = &mapping.right_expr
+        else {
+            unreachable!()
+        };
+
+        let left = branch_tokens(&mapping.left_key, mapping.left_value.as_ref());
+        let right = branch_tokens(else_key, else_value.as_ref());
+
+        let nested_code = ok_or_emit!(
+            tokens,
+            multi_tokens(sinks, iter_clauses, |names| {
+                let insert_left = sinks[0].insert_tokens(&names[0], left);
+                let insert_right = sinks[1].insert_tokens(&names[1], right);
+                quote! {
+                    if #conditions {
+                        #insert_left
+                    } else {
+                        #insert_right
+                    }
+                }
+            })
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for PartitionComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let sinks_span = input.span();
+        let sinks = parse_sinks(input)?;
+        if sinks.len() != 2 {
+            return Err(syn::Error::new(
+                sinks_span,
+                "partition! needs exactly two collections",
+            ));
+        }
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+        if mapping.right_expr.is_none() {
+            return Err(syn::Error::new_spanned(
+                &mapping.left_key,
+                "partition! needs a mapping of the form `a if condition else b`",
+            ));
+        }
+
+        Ok(Self {
+            sinks,
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+/*-----------------UnzipComprehension------------------- */
+// unzip![(vector, hash_set); (a, b) for ...]
+// 映射为元组, 第i个元素放入第i个集合
+#[derive(Debug)]
+pub struct UnzipComprehension {
+    pub sinks: Vec<SinkKind>,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for UnzipComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let UnzipComprehension {
+            sinks,
+            mapping,
+            iter_clauses,
+        } = self;
+
+        let value = ok_or_emit!(tokens, mapping.value_tokens("unzip!"));
+
+        let nested_code = ok_or_emit!(
+            tokens,
+            multi_tokens(sinks, iter_clauses, |names| {
+                let parts: Vec<syn::Ident> = (0..sinks.len())
+                    .map(|i| format_ident!("__unzip_part_{}", i))
+                    .collect();
+                let inserts = sinks
+                    .iter()
+                    .zip(names)
+                    .zip(&parts)
+                    .map(|((sink, name), part)| sink.insert_tokens(name, quote! { #part }));
+                quote! {
+                    let ( #(#parts,)* ) = #value;
+                    #(#inserts)*
+                }
+            })
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for UnzipComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let sinks_span = input.span();
+        let sinks = parse_sinks(input)?;
+        if sinks.len() < 2 {
+            return Err(syn::Error::new(
+                sinks_span,
+                "unzip! needs at least two collections",
+            ));
+        }
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            sinks,
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_multi_parse() {
+        let partition: PartitionComprehension = parse_quote! {
+            (vector, hash_map); x if x > 0 else x => 1 for x in items
+        };
+        assert_eq!(partition.sinks, vec![SinkKind::Vector, SinkKind::HashMap]);
+        assert!(partition.mapping.right_expr.is_some());
+        eprintln!("PartitionComprehension测试通过");
+
+        let unzip: UnzipComprehension = parse_quote! {
+            (vector, hash_set, b_tree_map); (a, b, (a, b)) for (a, b) in pairs
+        };
+        assert_eq!(unzip.sinks.len(), 3);
+        eprintln!("UnzipComprehension测试通过");
+
+        assert!(
+            syn::parse2::<UnzipComprehension>(quote! { (vec, hash_set); x for x in y }).is_err()
+        );
+        eprintln!("未知集合类型测试通过");
+
+        // 集合的个数与映射的形式在解析时检查
+        let error = syn::parse2::<PartitionComprehension>(
+            quote! { (vector); x if x > 0 else 0 for x in y },
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("exactly two collections"));
+        let error =
+            syn::parse2::<PartitionComprehension>(quote! { (vector, vector); x for x in y })
+                .unwrap_err();
+        assert!(error.to_string().contains("`a if condition else b`"));
+        let error =
+            syn::parse2::<UnzipComprehension>(quote! { (vector); x for x in y }).unwrap_err();
+        assert!(error.to_string().contains("at least two collections"));
+        eprintln!("集合个数与映射形式测试通过");
+    }
+}
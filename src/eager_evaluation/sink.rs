@@ -0,0 +1,235 @@
+use crate::capacity_clause::CapacityClause;
+use crate::iter_clause::IterClause;
+use crate::mapping::Mapping;
+use quote::quote;
+use syn::parse::ParseStream;
+
+/*-----------------SinkComprehension------------------- */
+// sink![Type; mapping for ... with capacity n]
+// 目标类型需要实现 better_comprehension_runtime::ComprehensionSink
+#[derive(Debug)]
+pub struct SinkComprehension {
+    pub target: Option<syn::Type>,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+    pub capacity: Option<CapacityClause>,
+}
+
+impl quote::ToTokens for SinkComprehension {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let SinkComprehension {
+            target,
+            mapping,
+            iter_clauses,
+            capacity,
+        } = self;
+
+        // 键值对的映射产生 (键, 值) 元组
+        let item = ok_or_emit!(
+            tokens,
+            match mapping.left_value {
+                Some(_) => mapping.pair_tokens(),
+                None => mapping.value_tokens("sink!"),
+            }
+        );
+        let target = match target {
+            Some(target) => quote! { #target },
+            None => quote! { _ },
+        };
+        let sink = quote! { ::better_comprehension_runtime::ComprehensionSink };
+
+        // 给出了容量时直接作为构造的提示, 否则根据size_hint调用reserve_hint
+        let (new_sink, reserve) = match capacity {
+            Some(CapacityClause { capacity, .. }) => (
+                quote! { <#target as #sink>::with_capacity_hint(#capacity) },
+                None,
+            ),
+            None => (
+                quote! { <#target as #sink>::with_capacity_hint(0) },
+                Some(quote! {
+                    #sink::reserve_hint(&mut __sink_comprehension, __comprehension_capacity);
+                }),
+            ),
+        };
+
+        let mut nested_code = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_nested_loops_with_reserve(
+                iter_clauses,
+                quote! { #sink::accept(&mut __sink_comprehension, #item); },
+                reserve,
+            )
+        );
+        nested_code = quote! {
+            {
+                let mut __sink_comprehension = #new_sink;
+                #nested_code
+                __sink_comprehension
+            }
+        };
+
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for SinkComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let target = crate::eager_evaluation::collect::parse_target(input)?;
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+        let capacity = crate::capacity_clause::parse_capacity(input)?;
+
+        Ok(Self {
+            target,
+            mapping,
+            iter_clauses,
+            capacity,
+        })
+    }
+}
+
+/*-----------------derive(Comprehension)------------------- */
+// 为实现了ComprehensionSink的类型生成专用的推导式宏, 宏转发给sink!
+// #[comprehension(macro_name = name)] 指定宏名, 默认为类型名的snake_case
+// #[comprehension(path = crate::x::Type)] 指定宏中使用的类型路径, 默认为裸的类型名
+// #[comprehension(export)] 为宏添加 #[macro_export], 此时必须给出path
+pub(crate) fn derive_comprehension(
+    input: syn::DeriveInput,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let mut macro_name = None;
+    let mut path = None;
+    let mut export = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("comprehension"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("macro_name") {
+                macro_name = Some(meta.value()?.parse::<syn::Ident>()?);
+                Ok(())
+            } else if meta.path.is_ident("path") {
+                path = Some(meta.value()?.parse::<syn::Path>()?);
+                Ok(())
+            } else if meta.path.is_ident("export") {
+                export = Some(meta.path.clone());
+                Ok(())
+            } else {
+                Err(meta.error("expected `macro_name = ...`, `path = ...` or `export`"))
+            }
+        })?;
+    }
+    // 导出的宏可能在任何模块中调用, 裸的类型名不一定在作用域内
+    if let (Some(export), None) = (&export, &path) {
+        return Err(syn::Error::new_spanned(
+            export,
+            "`export` requires `path = ...` so that the macro can name the type from any module",
+        ));
+    }
+
+    let ident = &input.ident;
+    let macro_name = macro_name
+        .unwrap_or_else(|| syn::Ident::new(&snake_case(&ident.to_string()), ident.span()));
+
+    // 泛型参数全部交给类型推断
+    let generics = input.generics.params.iter().map(|param| match param {
+        syn::GenericParam::Lifetime(_) => quote! { '_ },
+        syn::GenericParam::Type(_) | syn::GenericParam::Const(_) => quote! { _ },
+    });
+    // 宏中的crate指向调用处的crate, 需要换成$crate
+    let path = match path {
+        Some(path) => {
+            let leading_colon = path.leading_colon;
+            let segments = path
+                .segments
+                .iter()
+                .map(|segment| match segment.ident == "crate" {
+                    true => quote! { $crate },
+                    false => quote! { #segment },
+                });
+            quote! { #leading_colon #(#segments)::* }
+        }
+        None => quote! { #ident },
+    };
+    let target = match input.generics.params.is_empty() {
+        true => path,
+        false => quote! { #path<#(#generics),*> },
+    };
+    let export = match export {
+        Some(_) => quote! { #[macro_export] },
+        None => quote! { #[allow(unused_macros)] },
+    };
+
+    Ok(quote! {
+        #export
+        macro_rules! #macro_name {
+            ($($tokens:tt)*) => {
+                ::better_comprehension_runtime::sink![#target; $($tokens)*]
+            };
+        }
+    })
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in name.char_indices() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_sink_parse() {
+        let sink: SinkComprehension = parse_quote! {
+            InternedSet; s.as_str() for s in names if !s.is_empty() with capacity 16
+        };
+        assert!(sink.target.is_some());
+        assert!(sink.capacity.is_some());
+        eprintln!("SinkComprehension解析测试通过");
+
+        assert_eq!(snake_case("SparseVec"), "sparse_vec");
+        assert_eq!(snake_case("Set"), "set");
+        eprintln!("snake_case测试通过");
+    }
+
+    #[test]
+    fn test_derive_comprehension() {
+        let input: syn::DeriveInput = parse_quote! {
+            #[comprehension(macro_name = sparse, path = crate::sparse::SparseVec, export)]
+            struct SparseVec<'a, T> {
+                items: Vec<(usize, &'a T)>,
+            }
+        };
+        let tokens = derive_comprehension(input).unwrap().to_string();
+        assert!(tokens.contains("macro_export"));
+        assert!(tokens.contains("macro_rules ! sparse"));
+        assert!(tokens.contains("$ crate :: sparse :: SparseVec < '_ , _ >"));
+        eprintln!("derive(Comprehension)测试通过");
+
+        let input: syn::DeriveInput = parse_quote! {
+            #[comprehension(export)]
+            struct SparseVec;
+        };
+        let error = derive_comprehension(input).unwrap_err();
+        assert!(error.to_string().contains("`export` requires `path = ...`"));
+        eprintln!("derive(Comprehension)导出缺少path测试通过");
+
+        let input: syn::DeriveInput = parse_quote! {
+            #[comprehension(unknown)]
+            struct InternedSet;
+        };
+        assert!(derive_comprehension(input).is_err());
+        eprintln!("derive(Comprehension)错误属性测试通过");
+    }
+}
@@ -0,0 +1,187 @@
+use crate::eager_evaluation::partition::SinkKind;
+use crate::iter_clause::IterClause;
+use crate::mapping::Mapping;
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::Token;
+use syn::parse::ParseStream;
+
+mod keyword {
+    syn::custom_keyword!(with);
+    syn::custom_keyword!(context);
+}
+
+// 目标集合: 标准库集合的宏名, 或与sink!相同的实现了ComprehensionSink的类型
+#[derive(Debug)]
+pub enum ValidateSink {
+    Kind(SinkKind),
+    Type(Box<syn::Type>),
+}
+
+impl syn::parse::Parse for ValidateSink {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let fork = input.fork();
+        if fork.parse::<SinkKind>().is_ok() && (fork.peek(Token![;]) || fork.peek(keyword::with)) {
+            return Ok(Self::Kind(input.parse::<SinkKind>()?));
+        }
+        Ok(Self::Type(Box::new(input.parse::<syn::Type>()?)))
+    }
+}
+
+impl ValidateSink {
+    fn new_tokens(&self) -> TokenStream {
+        match self {
+            Self::Kind(kind) => kind.new_tokens(),
+            Self::Type(ty) => quote! {
+                <#ty as ::better_comprehension_runtime::ComprehensionSink>::with_capacity_hint(0)
+            },
+        }
+    }
+
+    fn insert_tokens(&self, collection: &syn::Ident, item: TokenStream) -> TokenStream {
+        match self {
+            Self::Kind(kind) => kind.insert_tokens(collection, item),
+            Self::Type(_) => quote! {
+                ::better_comprehension_runtime::ComprehensionSink::accept(&mut #collection, #item);
+            },
+        }
+    }
+}
+
+/*-----------------ValidateComprehension------------------- */
+// validate![vector; result for ...], 映射产生 Result<T, E>
+// 遍历所有元素, 全部为Ok时结果为 Ok(集合), 否则为 Err(Vec<E>)
+// validate![vector with context; ...] 时错误为 (模式变量, E), 多个模式变量组成元组
+#[derive(Debug)]
+pub struct ValidateComprehension {
+    pub sink: ValidateSink,
+    pub with_context: bool,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for ValidateComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let ValidateComprehension {
+            sink,
+            with_context,
+            mapping,
+            iter_clauses,
+        } = self;
+
+        let result = ok_or_emit!(tokens, mapping.value_tokens("validate!"));
+        let collection = format_ident!("__validate_comprehension");
+        let insert = sink.insert_tokens(&collection, quote! { __validate_value });
+        let error = match with_context {
+            true => {
+                let context = context_tokens(iter_clauses);
+                quote! { (#context, __validate_error) }
+            }
+            false => quote! { __validate_error },
+        };
+
+        let nested_code = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_nested_loops(
+                iter_clauses,
+                quote! {
+                    match #result {
+                        ::std::result::Result::Ok(__validate_value) => {
+                            #insert
+                        }
+                        ::std::result::Result::Err(__validate_error) => {
+                            __validate_errors.push(#error);
+                        }
+                    }
+                },
+            )
+        );
+        let new_collection = sink.new_tokens();
+
+        tokens.extend(quote! {
+            {
+                let mut #collection = #new_collection;
+                let mut __validate_errors = ::std::vec::Vec::new();
+                #nested_code
+                if __validate_errors.is_empty() {
+                    ::std::result::Result::Ok(#collection)
+                } else {
+                    ::std::result::Result::Err(__validate_errors)
+                }
+            }
+        });
+    }
+}
+
+// 按子句顺序收集for模式绑定的变量, 一个时为变量本身, 否则为元组
+fn context_tokens(iter_clauses: &[IterClause]) -> TokenStream {
+    let idents: Vec<syn::Ident> = iter_clauses
+        .iter()
+        .flat_map(|iter_clause| iter_clause.for_in_clause.bindings())
+        .collect();
+    match idents.as_slice() {
+        [ident] => quote! { #ident },
+        idents => quote! { ( #(#idents,)* ) },
+    }
+}
+
+impl syn::parse::Parse for ValidateComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let sink = input.parse::<ValidateSink>()?;
+        let with_context = input.peek(keyword::with);
+        if with_context {
+            input.parse::<keyword::with>()?;
+            input.parse::<keyword::context>()?;
+        }
+        input.parse::<Token![;]>()?;
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            sink,
+            with_context,
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_validate_parse() {
+        let validate: ValidateComprehension = parse_quote! {
+            hash_map; parse(x) for x in items
+        };
+        assert!(matches!(
+            validate.sink,
+            ValidateSink::Kind(SinkKind::HashMap)
+        ));
+        assert!(!validate.with_context);
+        eprintln!("ValidateComprehension测试通过");
+
+        let validate: ValidateComprehension = parse_quote! {
+            vector with context; check(a, b) for (i, a) in rows for Some(b) in a if b > 0
+        };
+        assert!(validate.with_context);
+        assert_eq!(
+            context_tokens(&validate.iter_clauses).to_string(),
+            "(i , a , b ,)"
+        );
+        eprintln!("ValidateComprehension with context测试通过");
+
+        // 实现了ComprehensionSink的类型
+        let validate: ValidateComprehension = parse_quote! {
+            SparseVec with context; parse(x) for x in items
+        };
+        assert!(matches!(validate.sink, ValidateSink::Type(_)));
+        assert!(validate.with_context);
+        let validate: ValidateComprehension = parse_quote! {
+            BTreeMap<_, _>; parse(x) for x in items
+        };
+        assert!(matches!(validate.sink, ValidateSink::Type(_)));
+        eprintln!("ValidateComprehension自定义集合测试通过");
+    }
+}
@@ -1,5 +1,7 @@
+use crate::capacity_clause::CapacityClause;
 use crate::iter_clause::IterClause;
-use crate::mapping::{Mapping, MappingElse};
+use crate::mapping::Mapping;
+use crate::sequence_clause::{DistinctClause, OrderByClause, TakeClause};
 use quote::quote;
 use syn::parse::ParseStream;
 
@@ -8,51 +10,60 @@ use syn::parse::ParseStream;
 pub struct VecComprehension {
     pub mapping: Mapping,
     pub iter_clauses: Vec<IterClause>,
+    pub distinct: Option<DistinctClause>,
+    pub order_by: Option<OrderByClause>,
+    pub take: Option<TakeClause>,
+    pub capacity: Option<CapacityClause>,
 }
 
 impl quote::ToTokens for VecComprehension {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
         let VecComprehension {
-            mapping:
-                Mapping {
-                    left_key,
-                    left_value,
-                    right_expr,
-                },
+            mapping,
             iter_clauses,
+            distinct,
+            order_by,
+            take,
+            capacity,
         } = self;
 
-        if left_value.is_some() {
-            panic!("Vec isn't key-value collection");
-        }
-
-        let mut nested_code = match right_expr {
-            None => quote! {
-                __vector_comprehension.push(#left_key);
-            },
-            Some(MappingElse {
-                conditions,
-                else_key,
-                else_value,
-            }) => {
-                if else_value.is_some() {
-                    panic!("Vec isn't key-value collection");
-                }
+        let value = ok_or_emit!(tokens, mapping.value_tokens("Vec"));
 
-                quote! {
-                    if #conditions {
-                        __vector_comprehension.push(#left_key);
-                    } else {
-                        __vector_comprehension.push(#else_key);
-                    }
-                }
-            }
-        };
+        // 简单的形状直接生成迭代器管道
+        if distinct.is_none()
+            && order_by.is_none()
+            && take.is_none()
+            && capacity.is_none()
+            && let Some(pipeline) = crate::eager_evaluation::handle_pipeline(
+                iter_clauses,
+                value.clone(),
+                quote! { ::std::vec::Vec<_> },
+            )
+        {
+            tokens.extend(pipeline);
+            return;
+        }
 
-        nested_code = crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code);
+        let (new_collection, reserve) = crate::capacity_clause::handle_capacity(
+            capacity.as_ref(),
+            quote! { Vec },
+            quote! { __vector_comprehension },
+        );
+        let mut nested_code = ok_or_emit!(
+            tokens,
+            crate::sequence_clause::handle_sequence_clauses(
+                iter_clauses,
+                distinct.as_ref(),
+                order_by.as_ref(),
+                take.as_ref(),
+                reserve,
+                value,
+                |item| quote! { __vector_comprehension.push(#item); },
+            )
+        );
         nested_code = quote! {
             {
-                let mut __vector_comprehension = Vec::new();
+                let mut __vector_comprehension = #new_collection;
                 #nested_code
                 __vector_comprehension
             }
@@ -64,11 +75,18 @@ impl quote::ToTokens for VecComprehension {
 
 impl syn::parse::Parse for VecComprehension {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let (mapping, iter_clauses) = crate::common_parse(input);
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+        let distinct = crate::sequence_clause::parse_distinct(input)?;
+        let (order_by, take) = crate::sequence_clause::parse_order_by_take(input)?;
+        let capacity = crate::capacity_clause::parse_capacity(input)?;
 
         Ok(Self {
             mapping,
             iter_clauses,
+            distinct,
+            order_by,
+            take,
+            capacity,
         })
     }
 }
@@ -0,0 +1,256 @@
+use crate::iter_clause::IterClause;
+use crate::mapping::Mapping;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Expr;
+use syn::Token;
+use syn::parse::ParseStream;
+use syn::visit::Visit;
+
+// 闭包字面量直接展开为块, 这样参数类型可以从累加器和映射推断出来
+// 闭包体中的 return/?/break/continue 在块中含义不同, 此时与其他表达式(如函数路径)一样直接调用
+fn combine(function: &Expr, acc: TokenStream, item: TokenStream) -> TokenStream {
+    match function {
+        Expr::Closure(closure) if !has_control_flow(&closure.body) => {
+            let acc_pat = &closure.inputs[0];
+            let item_pat = &closure.inputs[1];
+            let body = &closure.body;
+            let body = match &closure.output {
+                syn::ReturnType::Default => quote! { #body },
+                syn::ReturnType::Type(_, ty) => {
+                    quote! { { let __fold_result: #ty = #body; __fold_result } }
+                }
+            };
+            quote! {
+                {
+                    let #acc_pat = #acc;
+                    let #item_pat = #item;
+                    #body
+                }
+            }
+        }
+        _ => quote! { (#function)(#acc, #item) },
+    }
+}
+
+// 嵌套的闭包, async块与条目有自己的控制流, 不需要检查
+// 宏的内容按逗号分隔的表达式分析, 无法解析时视为包含控制流
+fn has_control_flow(body: &Expr) -> bool {
+    struct Finder {
+        found: bool,
+    }
+    impl<'ast> Visit<'ast> for Finder {
+        fn visit_expr_return(&mut self, _: &'ast syn::ExprReturn) {
+            self.found = true;
+        }
+        fn visit_expr_try(&mut self, _: &'ast syn::ExprTry) {
+            self.found = true;
+        }
+        fn visit_expr_break(&mut self, _: &'ast syn::ExprBreak) {
+            self.found = true;
+        }
+        fn visit_expr_continue(&mut self, _: &'ast syn::ExprContinue) {
+            self.found = true;
+        }
+        fn visit_expr_closure(&mut self, _: &'ast syn::ExprClosure) {}
+        fn visit_expr_async(&mut self, _: &'ast syn::ExprAsync) {}
+        fn visit_item(&mut self, _: &'ast syn::Item) {}
+        fn visit_macro(&mut self, node: &'ast syn::Macro) {
+            match node
+                .parse_body_with(syn::punctuated::Punctuated::<Expr, Token![,]>::parse_terminated)
+            {
+                Ok(exprs) => exprs.iter().for_each(|expr| self.visit_expr(expr)),
+                Err(_) => self.found = true,
+            }
+        }
+    }
+
+    let mut finder = Finder { found: false };
+    finder.visit_expr(body);
+    finder.found
+}
+
+// fold函数: 闭包字面量必须恰好有两个参数(累加器与元素)
+fn parse_function(input: ParseStream) -> syn::Result<Expr> {
+    let function = input.parse::<Expr>()?;
+    if let Expr::Closure(closure) = &function
+        && closure.inputs.len() != 2
+    {
+        return Err(syn::Error::new_spanned(
+            closure,
+            "the fold function must take exactly two parameters",
+        ));
+    }
+    Ok(function)
+}
+
+/*-----------------FoldComprehension------------------- */
+#[derive(Debug)]
+pub struct FoldComprehension {
+    pub init: Expr,
+    pub function: Expr,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for FoldComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let FoldComprehension {
+            init,
+            function,
+            mapping,
+            iter_clauses,
+        } = self;
+
+        let value = ok_or_emit!(tokens, mapping.value_tokens("fold!"));
+        let combined = combine(function, quote! { __fold_comprehension }, quote! { #value });
+
+        let mut nested_code = quote! {
+            __fold_comprehension = #combined;
+        };
+
+        nested_code = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code)
+        );
+        nested_code = quote! {
+            {
+                let mut __fold_comprehension = #init;
+                #nested_code
+                __fold_comprehension
+            }
+        };
+
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for FoldComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let init = input.parse::<Expr>()?;
+        input.parse::<Token![,]>()?;
+        let function = parse_function(input)?;
+        input.parse::<Token![;]>()?;
+
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            init,
+            function,
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+/*-----------------ReduceComprehension------------------- */
+#[derive(Debug)]
+pub struct ReduceComprehension {
+    pub function: Expr,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for ReduceComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let ReduceComprehension {
+            function,
+            mapping,
+            iter_clauses,
+        } = self;
+
+        let value = ok_or_emit!(tokens, mapping.value_tokens("reduce!"));
+        let combined = combine(
+            function,
+            quote! { __reduce_accumulator },
+            quote! { __reduce_item },
+        );
+
+        let mut nested_code = quote! {
+            let __reduce_item = #value;
+            __reduce_comprehension = ::std::option::Option::Some(
+                match __reduce_comprehension.take() {
+                    ::std::option::Option::None => __reduce_item,
+                    ::std::option::Option::Some(__reduce_accumulator) => #combined,
+                }
+            );
+        };
+
+        nested_code = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code)
+        );
+        nested_code = quote! {
+            {
+                let mut __reduce_comprehension = ::std::option::Option::None;
+                #nested_code
+                __reduce_comprehension
+            }
+        };
+
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for ReduceComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let function = parse_function(input)?;
+        input.parse::<Token![;]>()?;
+
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            function,
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_fold_parse() {
+        // 测试闭包形式的fold解析
+        let fold: FoldComprehension = parse_quote! {
+            String::new(), |acc, s| acc + s; x.as_str() for x in items if !x.is_empty()
+        };
+        assert!(matches!(fold.init, Expr::Call(_)));
+        assert!(matches!(fold.function, Expr::Closure(_)));
+        assert!(matches!(fold.mapping.left_key, Expr::MethodCall(_)));
+        assert_eq!(fold.iter_clauses.len(), 1);
+        eprintln!("FoldComprehension闭包形式测试通过");
+
+        // 测试函数路径形式的reduce解析
+        let reduce: ReduceComprehension = parse_quote! {
+            i32::max; x * y for x in 1..3 for y in 1..3
+        };
+        assert!(matches!(reduce.function, Expr::Path(_)));
+        assert_eq!(reduce.iter_clauses.len(), 2);
+        eprintln!("ReduceComprehension函数路径形式测试通过");
+
+        // 参数个数错误的闭包在解析时报告错误
+        let error =
+            syn::parse2::<ReduceComprehension>(quote! { |a| a; x for x in items }).unwrap_err();
+        assert!(error.to_string().contains("exactly two parameters"));
+        eprintln!("fold函数参数个数测试通过");
+
+        // 含有 return 或 ? 的闭包被直接调用, 而不是展开为块
+        let closure: Expr = parse_quote! { |acc, x| acc.checked_add(x?) };
+        assert!(
+            combine(&closure, quote! { a }, quote! { b })
+                .to_string()
+                .starts_with("(|")
+        );
+        let closure: Expr = parse_quote! { |acc, x| acc + x.iter().map(|y| y?).sum::<i32>() };
+        assert!(
+            !combine(&closure, quote! { a }, quote! { b })
+                .to_string()
+                .starts_with("(|")
+        );
+        eprintln!("fold闭包控制流测试通过");
+    }
+}
@@ -1,154 +1,435 @@
-use crate::iter_clause::{BareIfClause, ForInClause, IterClause, LetClause};
-use crate::mapping::{Mapping, MappingElse};
+use crate::iter_clause::{BareIfClause, ForInClause, IterClause, JoinClause, LetClause};
+use crate::mapping::Mapping;
+use crate::sequence_clause::DistinctClause;
 
-use quote::quote;
+use quote::{format_ident, quote, quote_spanned};
 use syn::Expr;
 use syn::parse::ParseStream;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
 
 /*-----------------RefIterator------------------- */
 #[derive(Debug)]
 pub struct IteratorRef {
     pub mapping: Mapping,
     pub iter_clauses: Vec<IterClause>,
-}
-
-struct InfoContainer<'a> {
-    depth: usize,
-    paths: Vec<&'a Expr>,
+    pub distinct: Option<DistinctClause>,
 }
 
 impl quote::ToTokens for IteratorRef {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        // 解构以获得变量
         let IteratorRef {
-            mapping:
-                Mapping {
-                    left_key,
-                    left_value,
-                    right_expr,
-                },
+            mapping,
             iter_clauses,
+            distinct,
         } = self;
 
-        if left_value.is_some() {
-            panic!("IteratorRef isn't key-value collection");
-        }
+        tokens.extend(handle_lazy(mapping, iter_clauses, distinct.as_ref(), false));
+    }
+}
 
-        let mut nested_code = match right_expr {
-            None => quote! {
-                #left_key
-            },
-            Some(MappingElse {
-                conditions,
-                else_key,
-                else_value,
-            }) => {
-                if else_value.is_some() {
-                    panic!("IteratorRef isn't key-value collection");
-                }
+/*-----------------OwnedIterator------------------- */
+// iterator_owned![...], 与iterator_ref!的语法相同, 但将可迭代对象移动进迭代器
+// 最外层按值遍历, 内层的集合在外层的每次迭代中被克隆, 因此产生拥有所有权(或克隆)的元素
+// 可以作为 `impl Iterator` 从消耗其参数的函数中返回
+#[derive(Debug)]
+pub struct IteratorOwned {
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+    pub distinct: Option<DistinctClause>,
+}
 
-                quote! {
-                    if #conditions {
-                        #left_key
-                    } else {
-                        #else_key
-                    }
-                }
-            }
-        };
+impl quote::ToTokens for IteratorOwned {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let IteratorOwned {
+            mapping,
+            iter_clauses,
+            distinct,
+        } = self;
 
-        let mut info_container = InfoContainer {
-            depth: 0,
-            paths: vec![],
-        };
+        tokens.extend(handle_lazy(mapping, iter_clauses, distinct.as_ref(), true));
+    }
+}
+
+struct InfoContainer<'a> {
+    depth: usize,
+    // 需要引用化的容器(被遮蔽为其引用)
+    paths: Vec<&'a Expr>,
+    // 在迭代器外求值一次的可迭代对象的声明
+    hoisted: Vec<proc_macro2::TokenStream>,
+    // 每层闭包中需要克隆的变量
+    captures: Vec<proc_macro2::TokenStream>,
+}
+
+fn handle_lazy(
+    mapping: &Mapping,
+    iter_clauses: &[IterClause],
+    distinct: Option<&DistinctClause>,
+    owned: bool,
+) -> proc_macro2::TokenStream {
+    let name = match owned {
+        true => "iterator_owned!",
+        false => "iterator_ref!",
+    };
+    let mut nested_code = match mapping.value_tokens(name) {
+        Ok(value) => value,
+        Err(error) => return error.to_compile_error(),
+    };
+
+    // distinct by key 时, 最内层产生(键, 值), 在展平之后过滤
+    if let Some(DistinctClause { key: Some(key), .. }) = distinct {
+        nested_code = quote! { (#key, #nested_code) };
+    }
+
+    let mut info_container = InfoContainer {
+        depth: 0,
+        paths: vec![],
+        hoisted: vec![],
+        captures: vec![],
+    };
+
+    // 从尾部遍历iter_clauses(从最内层开始)
+    let mut iter_clauses: Vec<&IterClause> = iter_clauses.iter().collect();
+    while let Some(iter_clause) = iter_clauses.pop() {
+        let IterClause {
+            for_in_clause: ForInClause { pat, iterable, .. },
+            if_clause,
+            let_clauses,
+            ..
+        } = iter_clause;
+        info_container.depth += 1;
+
+        // 外层模式与let子句绑定的变量在每次迭代中都是新的, 使用它们的可迭代对象在每次迭代中重新求值
+        let outer_lets: Vec<syn::Ident> = iter_clauses
+            .iter()
+            .flat_map(|outer| outer.let_clauses.iter().flat_map(LetClause::bindings))
+            .collect();
+        let outer_bindings: Vec<syn::Ident> = iter_clauses
+            .iter()
+            .flat_map(|outer| outer.for_in_clause.bindings())
+            .chain(outer_lets.iter().cloned())
+            .collect();
+        let is_bound = mentions(iterable, &outer_bindings);
+        // let绑定的值属于外层的闭包, 借用它的迭代器不能从闭包中返回
+        // 因此从这一层开始在闭包中求值, 结果收集到Vec中
+        let borrows_let = !owned
+            && !matches!(iterable, Expr::Path(_) | Expr::Range(_))
+            && mentions(iterable, &outer_lets);
 
-        // 从尾部遍历iter_clauses(从最内层开始)
-        let mut iter_clauses: Vec<&IterClause> = iter_clauses.iter().collect();
-        while let Some(iter_clause) = iter_clauses.pop() {
-            let IterClause {
-                for_in_clause: ForInClause { pat, iterable },
-                if_clause,
-                let_clauses,
-            } = iter_clause;
-            info_container.depth += 1;
-
-            nested_code = {
-                //
-                match iterable {
-                    Expr::Range(_) => {}
-                    Expr::Path(_) => {
+        nested_code = {
+            let iterable_code = match (owned, iterable) {
+                (false, _) if is_bound => quote! { #iterable },
+                (false, Expr::Range(_)) => quote! { #iterable },
+                // 单一标识符被遮蔽为它的引用, 引用是Copy的, 可以直接移动进每层闭包
+                (false, Expr::Path(path)) if path.path.get_ident().is_some() => {
+                    if !info_container.paths.contains(&iterable) {
                         info_container.paths.push(iterable);
                     }
-                    _ => panic!(
-                        "Only range (e.g. 1..10 or 2..=x where x is a number) and single identifier \
-                        (without any method calls) are supported for iterable comprehension"
-                    ),
+                    quote! { #iterable.iter() }
                 }
-
-                let conditions = match if_clause {
-                    Some(BareIfClause { conditions }) => quote! { #conditions },
-                    None => quote! { true },
-                };
-
-                let mut let_clauses: Vec<&LetClause> = let_clauses.iter().collect();
-                while let Some(LetClause { let_expr }) = let_clauses.pop() {
-                    nested_code = quote! {
-                        #let_expr;
-                        #nested_code
-                    };
+                (
+                    false,
+                    Expr::Lit(_)
+                    | Expr::Closure(_)
+                    | Expr::Assign(_)
+                    | Expr::Let(_)
+                    | Expr::Return(_)
+                    | Expr::Break(_)
+                    | Expr::Continue(_),
+                ) => {
+                    return syn::Error::new_spanned(
+                        iterable,
+                        "expected a collection, a reference or an iterator that implements Clone",
+                    )
+                    .to_compile_error();
                 }
+                // 引用(包括字段, 索引与路径的引用)可以直接移动进每层闭包; 其他表达式的迭代器需要实现Clone
+                // 克隆的代码使用可迭代对象的span, 未实现Clone时错误指向该表达式
+                (false, _) => {
+                    let ident = format_ident!("__iterator_ref_{}", info_container.hoisted.len());
+                    match iterable {
+                        Expr::Reference(_) => {
+                            info_container
+                                .hoisted
+                                .push(quote! { let #ident = #iterable; });
+                        }
+                        Expr::Field(_) | Expr::Index(_) | Expr::Path(_) => {
+                            info_container
+                                .hoisted
+                                .push(quote! { let #ident = &#iterable; });
+                        }
+                        _ => {
+                            info_container.hoisted.push(quote! {
+                                let #ident = ::std::iter::IntoIterator::into_iter(#iterable);
+                            });
+                            let spanned = syn::Ident::new(&ident.to_string(), iterable.span());
+                            info_container
+                                .captures
+                                .push(quote_spanned! { iterable.span()=>
+                                    let #ident = ::std::clone::Clone::clone(&#spanned);
+                                });
+                        }
+                    }
+                    quote! { #ident }
+                }
+                // 最外层直接移动; 外层模式绑定的变量同样直接移动
+                // 其余的内层变量是被捕获的集合, 需要在每次迭代中克隆
+                (true, Expr::Path(_)) if !iter_clauses.is_empty() && !is_bound => {
+                    info_container
+                        .captures
+                        .push(quote! { let #iterable = #iterable.clone(); });
+                    quote! { #iterable }
+                }
+                // 内层的其他表达式可能借用被捕获的集合, 这样的迭代器不能从闭包中返回
+                (true, _) if !iter_clauses.is_empty() && !is_bound && !is_range(iterable) => {
+                    return syn::Error::new_spanned(
+                        iterable,
+                        "inner iterables of iterator_owned! must be identifiers, ranges \
+                        or expressions of variables bound by outer clauses",
+                    )
+                    .to_compile_error();
+                }
+                (true, _) => quote! { #iterable },
+            };
 
+            let mut let_clauses: Vec<&LetClause> = let_clauses.iter().collect();
+            while let Some(LetClause { let_expr, .. }) = let_clauses.pop() {
                 nested_code = quote! {
-                    (#iterable)
+                    #let_expr;
+                    #nested_code
+                };
+            }
+
+            // 没有条件的层使用map, 保留可迭代对象的size_hint(单层时为ExactSizeIterator)
+            nested_code = match if_clause {
+                Some(BareIfClause { conditions, .. }) => quote! {
+                    (#iterable_code)
                     .into_iter()
                     .filter_map(move |#pat| {
                         ( #conditions ).then(|| {
                             #nested_code
                         })
                     })
+                },
+                None => quote! {
+                    (#iterable_code)
+                    .into_iter()
+                    .map(move |#pat| {
+                        #nested_code
+                    })
+                },
+            };
+
+            if borrows_let {
+                let flattens = (1..info_container.depth).map(|_| quote! { .flatten() });
+                nested_code = quote! {
+                    ::std::iter::Iterator::collect::<::std::vec::Vec<_>>(
+                        #nested_code #(#flattens)*
+                    )
                 };
+                info_container.depth = 1;
+            }
 
-                match iter_clauses.is_empty() {
-                    true => nested_code,
-                    false => {
-                        // 非最外层，需要处理克隆
-                        for iterable in &info_container.paths {
-                            nested_code = quote! {
-                                let #iterable = #iterable.clone();
-                                #nested_code
-                            }
-                        }
-                        nested_code
+            match iter_clauses.is_empty() {
+                true => nested_code,
+                false => {
+                    // 非最外层，需要处理克隆
+                    let captures = &info_container.captures;
+                    quote! {
+                        #(#captures)*
+                        #nested_code
                     }
                 }
-            };
+            }
+        };
+    }
+
+    // 将fliter_map的Some展开
+    for _ in 0..(info_container.depth - 1) {
+        nested_code = quote! { #nested_code.flatten() }
+    }
+
+    match distinct {
+        Some(DistinctClause { key: Some(_), .. }) => {
+            nested_code = quote! {
+                #nested_code.filter_map({
+                    let mut __distinct_seen = ::std::collections::HashSet::new();
+                    move |(__distinct_key, __distinct_item)| {
+                        __distinct_seen.insert(__distinct_key).then_some(__distinct_item)
+                    }
+                })
+            }
         }
+        Some(DistinctClause { key: None, .. }) => {
+            nested_code = quote! {
+                #nested_code.filter({
+                    let mut __distinct_seen = ::std::collections::HashSet::new();
+                    move |__distinct_item| {
+                        __distinct_seen.insert(::std::clone::Clone::clone(__distinct_item))
+                    }
+                })
+            }
+        }
+        None => {}
+    }
+
+    // 为需要引用化的容器添加声明, 不复制容器的元素
+    // 通过方法调用取得引用: 变量本身是引用时按值匹配, 得到的引用与原引用的生命周期相同
+    // 因此以引用为参数的函数可以返回该迭代器
+    if !info_container.paths.is_empty() {
+        let paths = &info_container.paths;
+        nested_code = quote! {
+            trait __IteratorRefBorrow {
+                fn __iterator_ref_borrow(&self) -> &Self {
+                    self
+                }
+            }
+            impl<T: ?Sized> __IteratorRefBorrow for T {}
+            #(let #paths = #paths.__iterator_ref_borrow();)*
+            #nested_code
+        }
+    }
 
-        // 将fliter_map的Some展开
-        for _ in 0..(info_container.depth - 1) {
-            nested_code = quote! { #nested_code.flatten() }
+    // 在迭代器外求值的可迭代对象需要在容器被引用化之前求值
+    let hoisted = info_container.hoisted.iter().rev();
+    quote! {
+        {
+            #(#hoisted)*
+            #nested_code
         }
+    }
+}
 
-        // 为需要引用化的容器添加声明
-        while let Some(iterable) = info_container.paths.last().copied() {
-            info_container.paths.pop();
-            nested_code = quote! {
-                let #iterable = #iterable.iter().collect::<Vec<_>>();
-                #nested_code
+// 范围(可以带括号)产生拥有所有权的元素, 不借用被捕获的变量
+fn is_range(expr: &Expr) -> bool {
+    match expr {
+        Expr::Range(_) => true,
+        Expr::Paren(paren) => is_range(&paren.expr),
+        _ => false,
+    }
+}
+
+// 表达式中是否使用了给定的变量
+fn mentions(expr: &Expr, idents: &[syn::Ident]) -> bool {
+    struct Finder<'a> {
+        idents: &'a [syn::Ident],
+        found: bool,
+    }
+    impl<'ast> Visit<'ast> for Finder<'_> {
+        fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+            if self.idents.iter().any(|ident| node.path.is_ident(ident)) {
+                self.found = true;
             }
         }
+    }
 
-        tokens.extend(quote! { { #nested_code } });
+    let mut finder = Finder {
+        idents,
+        found: false,
+    };
+    finder.visit_expr(expr);
+    finder.found
+}
+
+// 惰性推导式不支持的子句在解析时报告
+fn parse_lazy(
+    input: ParseStream,
+) -> syn::Result<(Mapping, Vec<IterClause>, Option<DistinctClause>)> {
+    let (mapping, iter_clauses) = crate::common_parse(input)?;
+    if let Some(JoinClause {
+        pat, iterable, on, ..
+    }) = iter_clauses
+        .iter()
+        .find_map(|iter_clause| iter_clause.join_clauses.first())
+    {
+        return Err(syn::Error::new_spanned(
+            quote! { #pat in #iterable on #on },
+            "join is not supported for iterator comprehension",
+        ));
+    }
+    if let Some(iter_clause) = iter_clauses
+        .iter()
+        .find(|iter_clause| iter_clause.for_in_clause.fallible)
+    {
+        return Err(syn::Error::new_spanned(
+            &iter_clause.for_in_clause.iterable,
+            "`in?` is not supported for iterator comprehension",
+        ));
     }
+    let distinct = crate::sequence_clause::parse_distinct(input)?;
+
+    Ok((mapping, iter_clauses, distinct))
 }
 
 impl syn::parse::Parse for IteratorRef {
     fn parse(input: ParseStream) -> syn::Result<Self> {
-        let (mapping, iter_clauses) = crate::common_parse(input);
+        let (mapping, iter_clauses, distinct) = parse_lazy(input)?;
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+            distinct,
+        })
+    }
+}
+
+impl syn::parse::Parse for IteratorOwned {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mapping, iter_clauses, distinct) = parse_lazy(input)?;
 
         Ok(Self {
             mapping,
             iter_clauses,
+            distinct,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_iterator_owned_inner_iterables() {
+        let expand = |owned: IteratorOwned| quote! { #owned }.to_string();
+
+        // 内层的标识符, 范围与外层绑定的表达式
+        assert!(!expand(parse_quote! { (x, y) for x in xs for y in ys }).contains("compile_error"));
+        assert!(
+            !expand(parse_quote! { (x, y) for x in 0..3 for y in (0..n) })
+                .contains("compile_error")
+        );
+        assert!(
+            !expand(parse_quote! { c for row in grid for c in row.into_iter().rev() })
+                .contains("compile_error")
+        );
+        eprintln!("iterator_owned!内层可迭代对象测试通过");
+
+        // 借用被捕获集合的内层表达式报告错误, 最外层不受限制
+        let expanded = expand(parse_quote! { (x, *y) for x in xs for y in ys.iter() });
+        assert!(expanded.contains("compile_error"));
+        assert!(expanded.contains("inner iterables of iterator_owned!"));
+        assert!(
+            !expand(parse_quote! { x for x in xs.into_iter().rev() }).contains("compile_error")
+        );
+        eprintln!("iterator_owned!不支持的内层可迭代对象测试通过");
+    }
+
+    #[test]
+    fn test_lazy_unsupported_clauses() {
+        let error = syn::parse2::<IteratorRef>(quote! {
+            (a, b) for a in xs join b in ys on a.id == b.id
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("join is not supported"));
+        eprintln!("惰性推导式join测试通过");
+
+        let error = syn::parse2::<IteratorOwned>(quote! {
+            x for x in? results
         })
+        .unwrap_err();
+        assert!(error.to_string().contains("`in?` is not supported"));
+        eprintln!("惰性推导式in?测试通过");
     }
 }
@@ -412,6 +412,755 @@ b_tree_map! :   insert() to add key-value pairs
 
 b_tree_set! :   insert() to add elements
 
+# Order by and take
+`vector!` `vec_deque!` `linked_list!` accept an `order by` clause and a `take` clause after all the `for in` clauses
+
+```ignore
+mapping
+<for pattern in iterable <if conditions>? <let expression>*>+
+<order by key <asc|desc>? <, key <asc|desc>?>*>?
+<take n>?
+```
+
+* The keys are evaluated in the innermost scope, so they can use every bound variable, and they must implement `Ord`
+* The sort is stable, elements with equal keys keep their iteration order
+* `take n` alone stops all the loops as soon as `n` elements are collected
+* `order by` with `take n` keeps a bounded `BinaryHeap` of `n` elements instead of sorting everything, O(len * log n)
+
+```rust
+use better_comprehension::{vector, linked_list};
+use std::collections::LinkedList;
+struct Person {
+    name: &'static str,
+    age: u32,
+}
+let people = [
+    Person { name: "Joe", age: 20 },
+    Person { name: "Bob", age: 25 },
+    Person { name: "Ann", age: 25 },
+    Person { name: "Tom", age: 30 },
+];
+
+let names = vector![
+    person.name
+    for person in &people if person.age > 20
+    order by person.age desc
+];
+// Bob and Ann have the same age, they keep their original order
+assert_eq!(names, vec!["Tom", "Bob", "Ann"]);
+
+let names = vector![
+    person.name
+    for person in &people
+    order by person.age desc, person.name
+];
+assert_eq!(names, vec!["Tom", "Ann", "Bob", "Joe"]);
+
+// the two youngest
+let youngest = linked_list![
+    person.name
+    for person in &people
+    order by person.age
+    take 2
+];
+assert_eq!(youngest, LinkedList::from(["Joe", "Bob"]));
+
+// stops after 3 elements
+let first_three = vector![x * y for x in (1..) for y in 1..=2 take 3];
+assert_eq!(first_three, vec![1, 2, 2]);
+```
+
+# Distinct
+`vector!` `vec_deque!` `linked_list!` `iterator_ref!` accept a `distinct` clause after all the `for in` clauses,
+which removes duplicates while keeping the iteration order (the first occurrence wins)
+
+* `distinct` uses the mapping itself as the key, the mapping must implement `Hash + Eq + Clone`
+* `distinct by key` uses `key` (evaluated in the innermost scope) as the key, the key must implement `Hash + Eq`, the mapping is only evaluated for new keys
+
+A hidden `HashSet` of the seen keys is kept in the generated code.
+In `vector!` `vec_deque!` `linked_list!`, `distinct` comes before `order by` and `take`, and it is applied first.
+The lazy iterators check each item against the same set in whichever direction it is taken,
+so `.rev()` on a `distinct` iterator keeps the *last* occurrence of each key
+
+```rust
+use better_comprehension::{vector, iterator_ref};
+struct User {
+    id: u32,
+    name: &'static str,
+}
+let users = [
+    User { id: 2, name: "Bob" },
+    User { id: 1, name: "Joe" },
+    User { id: 2, name: "Bobby" },
+    User { id: 3, name: "Ann" },
+];
+
+let names = vector![user.name for user in &users distinct by user.id];
+assert_eq!(names, vec!["Bob", "Joe", "Ann"]);
+
+let ids = vector![user.id for user in &users distinct];
+assert_eq!(ids, vec![2, 1, 3]);
+
+let smallest_ids = vector![user.id for user in &users distinct order by user.id take 2];
+assert_eq!(smallest_ids, vec![1, 2]);
+
+let mut names = iterator_ref![user.name for user in users distinct by user.id];
+assert_eq!(names.next(), Some("Bob"));
+assert_eq!(names.next(), Some("Joe"));
+assert_eq!(names.next(), Some("Ann"));
+assert_eq!(names.next(), None);
+
+// taken from the back, the last occurrence wins
+let names = iterator_ref![user.name for user in users distinct by user.id];
+assert_eq!(names.rev().collect::<Vec<_>>(), vec!["Ann", "Bobby", "Joe"]);
+```
+
+# Join
+Joining two collections with `for a in xs for b in ys if a.id == b.id` is O(len(xs) * len(ys)).
+A `join` clause after a `for in` clause builds a `HashMap` index over the joined collection once,
+then each outer element only needs one lookup
+
+```ignore
+for pattern in iterable
+<<left>? join pattern in iterable on outer_key == inner_key>*
+<if conditions>?
+<let expression>*
+```
+
+* The left side of `==` is the key of the outer elements, the right side is the key of the joined elements, both must be the same type and implement `Hash + Eq`
+* Several keys can be joined with `&&` (`on a.x == b.x && a.y == b.y`) or written as tuples (`on (a.x, a.y) == (b.x, b.y)`)
+* `left join` binds `Option` of the joined element, `None` when nothing matches
+* The joined iterable has the same ownership rules as a `for in` clause (`&ys` and `ys.iter()` are kept, `ys` is consumed), and it is evaluated only once, before all the loops, so it cannot use the variables bound by outer clauses (this is reported as an error)
+* The elements of the joined iterable must implement `Clone` (references always do): the index stores them and each match yields a clone, so join `&ys` to avoid cloning owned elements
+* `if` and `let` apply after all the joins of the clause
+
+```rust
+use better_comprehension::vector;
+struct Order {
+    id: u32,
+    customer_id: u32,
+}
+struct Customer {
+    id: u32,
+    name: &'static str,
+}
+let orders = vec![
+    Order { id: 1, customer_id: 10 },
+    Order { id: 2, customer_id: 20 },
+    Order { id: 3, customer_id: 10 },
+    Order { id: 4, customer_id: 30 },
+];
+let customers = vec![
+    Customer { id: 10, name: "Alice" },
+    Customer { id: 20, name: "Bob" },
+];
+
+let pairs = vector![
+    (order.id, customer.name)
+    for order in &orders
+    join customer in &customers on order.customer_id == customer.id
+];
+assert_eq!(pairs, vec![(1, "Alice"), (2, "Bob"), (3, "Alice")]);
+
+let pairs = vector![
+    (order.id, customer.map(|c| c.name))
+    for order in &orders
+    left join customer in &customers on order.customer_id == customer.id
+    if order.id > 1
+];
+assert_eq!(pairs, vec![(2, Some("Bob")), (3, Some("Alice")), (4, None)]);
+```
+
+# Capacity
+`vector!` `vec_deque!` `hash_set!` `hash_map!` `binary_heap!` pre-allocate their capacity
+
+* For a single `for in` clause without `if` and `join` (and without `distinct`), the number of elements is known,
+  the collection reserves the lower bound of the iterable's `size_hint` before the loop (at most `n` with `take n`)
+* Otherwise, a `with capacity n` clause after all the other clauses gives the capacity explicitly,
+  and the collection is created with `with_capacity(n)`
+
+`benches/capacity.rs` (`cargo bench --bench capacity`) compares these expansions with the previous `new()` ones.
+`hash_map!` is about 2.5x faster, `vector!` is within noise since growing a `Vec` is already cheap
+
+Shapes that only use `for in` and `if` clauses (no `let`, `join`, `distinct`, `order by`, `take` or `with capacity`)
+in `vector!` `vec_deque!` `linked_list!` `hash_set!` `hash_map!` are expanded to an iterator pipeline
+(`into_iter()` with `map` / `filter_map`, `flat_map` for nested clauses) followed by `collect()`,
+so the standard library's own pre-allocation and specialisations apply.
+When the mapping or the conditions contain `return` `break` `continue` `?` `.await`,
+or an inner clause refers to a local variable that is not bound by the patterns,
+the loop expansion is used instead, so the behavior and ownership are the same either way
+
+```rust
+use better_comprehension::{vector, hash_map};
+let data = vec![1, 2, 3, 4, 5, 6];
+
+// reserves data.len() from size_hint
+let doubled = vector![x * 2 for x in data.iter()];
+assert!(doubled.capacity() >= 6);
+
+let evens = vector![*x for x in data.iter() if x % 2 == 0 with capacity data.len() / 2];
+assert_eq!(evens, vec![2, 4, 6]);
+
+let table = hash_map![
+    (*x, y) => x * y
+    for x in data.iter()
+    for y in 1..=3
+    with capacity data.len() * 3
+];
+assert_eq!(table.len(), 18);
+```
+
+# Bulk construction
+`binary_heap!` `b_tree_map!` `b_tree_set!` buffer all the elements into a `Vec` first and then build the collection in bulk
+
+* `binary_heap!` uses `BinaryHeap::from(vec)`, an O(n) heapify instead of n `push`
+* `b_tree_map!` uses `FromIterator`, a stable sort followed by a bulk build. For duplicate keys the last one wins, the same as `insert`
+* `b_tree_set!` sorts and dedups the buffer first, so among equal elements the first one is kept, the same as `insert`
+
+The buffer holds every element including the duplicates, so the peak memory is proportional to the number of iterations.
+`benches/bulk.rs` (`cargo bench --bench bulk`) compares these expansions with the previous `insert` / `push` ones over 10,000 elements.
+`b_tree_map!` is about 2.4x faster on random keys and 9x on sorted keys, `b_tree_set!` 3x / 5.5x, `binary_heap!` 5x to 6x
+
+```rust
+use better_comprehension::{b_tree_map, binary_heap};
+use std::collections::BTreeMap;
+
+let map = b_tree_map![x % 3 => x for x in 0..10];
+assert_eq!(map, BTreeMap::from([(0, 9), (1, 7), (2, 8)]));
+
+let heap = binary_heap![x * y for x in 1..4 for y in 1..4 if x != y];
+assert_eq!(heap.into_sorted_vec(), vec![2, 2, 3, 3, 6, 6]);
+```
+
+# Collect into any type
+`collect!` builds any type that implements `FromIterator` (or `Default + Extend`), such as `String`, `Box<[T]>`, `Rc<[T]>`, `Cow` or `Result<Vec<T>, E>`
+
+* `collect![Type; mapping for ...]` gives the target type explicitly, without `Type;` it is inferred from the context
+* `collect![default Type; mapping for ...]` starts from `Type::default()` and puts the items in with `Extend` (like `extend!`), so types without `FromIterator` (such as arena-backed vectors) can be targets
+* key-value mappings produce `(key, value)` items
+* shapes that only use `for in` and `if` clauses are collected from an iterator pipeline,
+  so `Result` / `Option` stop evaluating at the first `Err` / `None`.
+  Other shapes buffer all the items into a `Vec` first,
+  when the given target type is `Result<..>` / `Option<..>` the loops stop at the first `Err` / `None` as well
+
+```rust
+use better_comprehension::collect;
+use std::collections::BTreeMap;
+
+let words = ["hello", "big", "world"];
+let string = collect![String; c.to_ascii_uppercase() for w in words.iter() for c in (w.chars())];
+assert_eq!(string, "HELLOBIGWORLD");
+
+let boxed = collect![Box<[usize]>; w.len() for w in words.iter()];
+assert_eq!(&*boxed, &[5, 3, 5]);
+
+let map: BTreeMap<&str, usize> = collect![*w => w.len() for w in words.iter()];
+assert_eq!(map[&"big"], 3);
+
+let inputs = ["1", "x"];
+let numbers = collect![Result<Vec<i32>, _>; s.parse::<i32>() for s in inputs.iter()];
+assert!(numbers.is_err());
+
+#[derive(Default)]
+struct Arena(Vec<usize>);
+impl Extend<usize> for Arena {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+let arena = collect![default Arena; w.len() for w in words.iter()];
+assert_eq!(arena.0, vec![5, 3, 5]);
+```
+
+# Custom collections
+The companion crate `better_comprehension_runtime` (in `runtime/`) provides the `ComprehensionSink` trait.
+Any type implementing it can be filled with `sink![Type; mapping for ...]` (`Type;` can be inferred),
+and `#[derive(Comprehension)]` generates a dedicated macro for it.
+The runtime crate re-exports all the macros of this crate, so it is the only dependency needed
+
+* `with_capacity_hint(n)` creates the collection, `n` comes from `with capacity n` or is `0`
+* `reserve_hint(n)` (optional) receives the lower bound of `size_hint` when the number of elements is known
+* `accept(item)` adds one element, key-value mappings produce `(key, value)` items
+* `#[comprehension(macro_name = name)]` chooses the macro name (the snake_case type name by default),
+  `#[comprehension(path = crate::module::Type)]` chooses the path the macro uses to name the type
+  (the bare type name by default, so the macro only works where the type is in scope),
+  `#[comprehension(export)]` adds `#[macro_export]` and requires `path`
+
+The std collections do not implement `ComprehensionSink`, they are filled by the dedicated macros (`vector!` `hash_map!` and so on)
+
+```ignore
+use better_comprehension_runtime::{Comprehension, ComprehensionSink};
+
+#[derive(Default, Comprehension)]
+struct SparseVec {
+    items: Vec<(usize, i32)>,
+}
+
+impl ComprehensionSink for SparseVec {
+    type Item = (usize, i32);
+    fn with_capacity_hint(capacity: usize) -> Self {
+        SparseVec { items: Vec::with_capacity(capacity) }
+    }
+    fn accept(&mut self, (index, value): (usize, i32)) {
+        if value != 0 {
+            self.items.push((index, value));
+        }
+    }
+}
+
+let dense = vec![0, 3, 0, 0, 7];
+let sparse = sparse_vec![i => *x for (i, x) in (dense.iter().enumerate())];
+assert_eq!(sparse.items, vec![(1, 3), (4, 7)]);
+```
+
+# Using the grammar in your own macros
+The syntax tree, the parser and the loop lowering live in the normal library crate `better_comprehension_syntax` (in `syntax/`),
+this crate is a thin wrapper that only decides how each macro expands.
+Other procedural macros can depend on it to accept the same grammar
+
+* `Comprehension` parses `mapping for pat in iterable ...`, and the `mapping` `iter_clause` `sequence_clause` `capacity_clause` modules hold the syntax tree
+* `lowering::handle_nested_loops` and `lowering::handle_pipeline` generate the nested loops or an iterator pipeline
+* the syntax tree types are `#[non_exhaustive]`, so new clauses can be added in minor versions
+
+# Extend an existing collection
+`extend![target; mapping for ...]` appends to a collection that already exists, and `extend_map![target; key => value for ...]` does the same for key-value collections
+
+* `target` is a place expression (`v`, `self.items`, `*r` for `r: &mut Vec<_>`), it is evaluated only once
+* elements go through the target's `Extend` implementation, so every collection uses the same `push` / `insert` / `push_back` as its own macro.
+  Any other `Extend` type (such as `String`) works too
+* shapes that only use `for in` and `if` clauses pass a single iterator pipeline to `extend`, so the target reserves capacity from its `size_hint`
+
+```rust
+use better_comprehension::{extend, extend_map};
+use std::collections::HashMap;
+
+let mut lines = vec![];
+for line in ["a b", "c"] {
+    extend![lines; word.to_string() for word in (line.split(' '))];
+}
+assert_eq!(lines, vec!["a", "b", "c"]);
+
+let mut config = HashMap::from([("a", 0), ("b", 0)]);
+let updates = [("b", 1), ("c", 2)];
+extend_map![config; *k => *v for (k, v) in updates.iter()];
+assert_eq!(config, HashMap::from([("a", 0), ("b", 1), ("c", 2)]));
+```
+
+# In-place mutation
+`update!` `retain!` `retain_map!` modify a collection in place without building a new one
+
+* `for x in ref mut collection` iterates with `collection.iter_mut()`, it works in every comprehension
+* `update![statement for ...]` runs the mapping once per element, `a if cond else b` picks one of two statements
+* `retain![v; x if cond]` expands to `v.retain(|x| cond)`, and `retain![ref mut v; x if cond]` to `v.retain_mut(..)`
+* `retain_map![m; k => v if cond]` expands to `m.retain(|k, v| cond)`, the value can be modified in the condition
+* `cond` can be a `let` chain, and `for` clauses after it apply `retain` to inner collections
+
+```rust
+use better_comprehension::{retain, retain_map, update};
+use std::collections::HashMap;
+
+let mut v = vec![-1, 2, 3];
+update![*x += 1 for x in ref mut v if *x > 0];
+assert_eq!(v, vec![-1, 3, 4]);
+
+let mut grid = vec![vec![1, -2, 3], vec![-4, 5]];
+update![*y *= 10 for row in ref mut grid for y in ref mut row if *y > 2];
+retain![*row; x if *x > 0 for row in ref mut grid];
+assert_eq!(grid, vec![vec![1, 30], vec![50]]);
+
+let mut stock = HashMap::from([("apple", 3), ("pear", 1)]);
+retain_map![stock; _ => count if { *count -= 1; *count > 0 }];
+assert_eq!(stock, HashMap::from([("apple", 2)]));
+```
+
+# Partition and unzip
+`partition!` and `unzip!` fill several collections in a single traversal and return them as a tuple.
+The collections are named after their macros: `vector` `vec_deque` `linked_list` `binary_heap` `hash_set` `b_tree_set` `hash_map` `b_tree_map`
+
+* `partition![(a, b); x if cond else y for ...]` puts `x` into `a` when `cond` holds and `y` into `b` otherwise.
+  Each branch can be a key-value pair for a map
+* `unzip![(a, b, ...); (x, y, ...) for ...]` puts the i-th element of the tuple mapping into the i-th collection.
+  Maps take `(key, value)` elements
+
+```rust
+use better_comprehension::{partition, unzip};
+use std::collections::{HashMap, HashSet};
+
+let data: [i32; 5] = [5, -3, 2, -8, 0];
+let (positive, rest) = partition![(vector, vector); *x if *x > 0 else *x for x in data.iter()];
+assert_eq!(positive, vec![5, 2]);
+assert_eq!(rest, vec![-3, -8, 0]);
+
+let (small, squares) = partition![(hash_set, hash_map); x.abs() if x.abs() < 5 else *x => x * x for x in data.iter()];
+assert_eq!(small, HashSet::from([3, 2, 0]));
+assert_eq!(squares, HashMap::from([(5, 25), (-8, 64)]));
+
+let pairs = [("a", 1), ("b", 2), ("a", 3)];
+let (names, parity) = unzip![(vector, hash_set); (*name, n % 2) for (name, n) in pairs.iter()];
+assert_eq!(names, vec!["a", "b", "a"]);
+assert_eq!(parity, HashSet::from([0, 1]));
+```
+
+# Fallible comprehensions
+Every eager macro has a `try_` variant: `try_vector!` `try_vec_deque!` `try_linked_list!` `try_binary_heap!` `try_hash_set!` `try_b_tree_set!` `try_hash_map!` `try_b_tree_map!` `try_collect!`
+
+They evaluate to `Result<Collection, E>`.
+`?` in the mapping, the conditions and the `let` clauses returns from the comprehension, not from the enclosing function, so the enclosing function does not need to return `Result`.
+Errors are converted into `E` with `From`, and `E` is inferred from the context.
+
+The comprehension runs inside an immediately invoked closure, which changes the meaning of a few other expressions in it:
+
+* `return` returns from that closure, not from the enclosing function
+* `break` and `continue` cannot target loops or labels outside the macro
+* `.await` is a compile error, even inside an `async fn`: await the values before the comprehension instead
+
+`for pattern in? iterable` unwraps each item with `?`, stopping at the first `Err`.
+It takes a variable by value, since `?` needs owned `Result`s.
+It also works in the plain macros, where `?` returns from the enclosing function as usual
+
+```rust
+use better_comprehension::{try_hash_map, try_vector};
+use std::collections::HashMap;
+use std::num::ParseIntError;
+
+let inputs = ["1", "2", "3"];
+let parsed: Result<Vec<i32>, ParseIntError> = try_vector![x.parse::<i32>()? * 2 for x in inputs.iter()];
+assert_eq!(parsed, Ok(vec![2, 4, 6]));
+
+let inputs = ["1", "x", "3"];
+let parsed: Result<Vec<i32>, ParseIntError> = try_vector![x.parse::<i32>()? for x in inputs.iter()];
+assert!(parsed.is_err());
+
+let results: Vec<Result<i32, String>> = vec![Ok(1), Err("bad".to_string()), Ok(3)];
+let positive: Result<Vec<i32>, String> = try_vector![x for x in? results if x > 0];
+assert_eq!(positive, Err("bad".to_string()));
+
+#[derive(Debug, PartialEq)]
+enum ConfigError {
+    Parse(ParseIntError),
+    Missing(String),
+}
+impl From<ParseIntError> for ConfigError {
+    fn from(error: ParseIntError) -> Self {
+        ConfigError::Parse(error)
+    }
+}
+fn lookup(key: &str) -> Result<&'static str, ConfigError> {
+    match key {
+        "port" => Ok("8080"),
+        "workers" => Ok("4"),
+        _ => Err(ConfigError::Missing(key.to_string())),
+    }
+}
+
+let keys = ["port", "workers"];
+let config: Result<HashMap<&str, u32>, ConfigError> = try_hash_map![
+    *key => value
+    for key in keys.iter()
+    let value = lookup(key)?.parse::<u32>()?
+];
+assert_eq!(config, Ok(HashMap::from([("port", 8080), ("workers", 4)])));
+
+let keys = ["port", "host"];
+let config: Result<HashMap<&str, u32>, ConfigError> = try_hash_map![
+    *key => lookup(key)?.parse::<u32>()?
+    for key in keys.iter()
+];
+assert_eq!(config, Err(ConfigError::Missing("host".to_string())));
+```
+
+# Optional comprehensions
+The `opt_` variants are the "all or nothing" counterpart of `try_` for `Option`: `opt_vector!` `opt_vec_deque!` `opt_linked_list!` `opt_binary_heap!` `opt_hash_set!` `opt_b_tree_set!` `opt_hash_map!` `opt_b_tree_map!` `opt_collect!`
+
+They evaluate to `Option<Collection>`.
+A `?` on `None` in the mapping, the conditions, the `let` clauses or `in?` makes the whole comprehension `None`.
+The caveats of the `try_` variants about `return`, `break` / `continue` and `.await` apply here as well.
+Use `if let` instead when the missing values should be skipped
+
+```rust
+use better_comprehension::{opt_vector, vector};
+use std::collections::HashMap;
+
+let prices = HashMap::from([("apple", 3), ("pear", 5)]);
+
+let keys = ["apple", "pear"];
+assert_eq!(opt_vector![*prices.get(k)? for k in keys.iter()], Some(vec![3, 5]));
+
+let keys = ["apple", "kiwi", "pear"];
+assert_eq!(opt_vector![*prices.get(k)? for k in keys.iter()], None);
+assert_eq!(
+    vector![*price for k in keys.iter() if let Some(price) = prices.get(k)],
+    vec![3, 5]
+);
+```
+
+# Validation
+`validate!` visits every element and collects every error instead of stopping at the first one.
+The mapping yields `Result<T, E>`, and the result is `Result<Collection<T>, Vec<E>>`.
+The collection is one of `vector` `vec_deque` `linked_list` `binary_heap` `hash_set` `b_tree_set` `hash_map` `b_tree_map`, and maps take `(key, value)` elements.
+It can also be a type implementing `ComprehensionSink` (see [Custom collections](#custom-collections)), as in `validate![SparseVec; ...]`.
+
+With `with context`, each error is paired with the pattern variables of the `for` clauses (a tuple when there are several of them)
+
+```rust
+use better_comprehension::validate;
+
+let inputs = ["1", "x", "3", "y"];
+let parsed = validate![vector; x.parse::<i32>() for x in inputs.iter()];
+assert_eq!(parsed.unwrap_err().len(), 2);
+
+let form = [("name", "Alice"), ("email", ""), ("city", "")];
+let checked = validate![
+    hash_map with context;
+    if value.is_empty() { Err("is empty") } else { Ok((*field, *value)) }
+    for (field, value) in form.iter()
+];
+let errors = vec![((&"email", &""), "is empty"), ((&"city", &""), "is empty")];
+assert_eq!(checked, Err(errors));
+```
+
+# Parallel comprehensions
+`par_vector!` `par_vec_deque!` `par_linked_list!` `par_binary_heap!` `par_hash_set!` `par_b_tree_set!` `par_hash_map!` `par_b_tree_map!` run a comprehension on several threads with `std::thread::scope`, without any dependency.
+
+The outermost iterable is split into chunks, and the workers take chunks one by one, building a partial collection for each chunk.
+The partial collections are then merged in input order, so the result is identical to the sequential macro: sequences keep the input order, and for maps a later key still overwrites an earlier one.
+
+* `with threads n` sets the number of workers (defaults to `std::thread::available_parallelism`)
+* `with chunk n` sets the number of outer elements per chunk (defaults to about four chunks per worker)
+
+The elements of the outermost iterable must be `Send`, and everything the inner clauses use is shared between the workers by reference.
+`in?` and `?` are not supported
+
+```rust
+use better_comprehension::{par_hash_map, par_vector, vector};
+
+let numbers: Vec<i32> = (0..1000).collect();
+let words = ["a", "b", "c"];
+
+let result = par_vector![
+    format!("{x}{w}")
+    for x in numbers.iter() if x % 7 == 0
+    for w in words.iter()
+    with threads 4
+    with chunk 16
+];
+assert_eq!(result, vector![format!("{x}{w}") for x in numbers.iter() if x % 7 == 0 for w in words.iter()]);
+
+let last_by_digit = par_hash_map![x % 10 => *x for x in numbers.iter()];
+assert_eq!(last_by_digit[&3], 993);
+```
+
+# Background producer
+`spawn_iter!` runs the nested loops on a spawned thread and returns an iterator that receives the results through a bounded `std::sync::mpsc::sync_channel`.
+The consumer gets each element as soon as it is produced, while the producer keeps working ahead of it.
+Unlike `iterator_ref!`, every clause of the eager comprehensions is available.
+
+* `with depth n` sets the capacity of the channel (defaults to 16; 0 hands over one element at a time)
+* Dropping the iterator stops the producer at its next element
+* A panic of the producer is resumed on the consumer when the iterator is exhausted
+* The closure of the thread is `move`, so the comprehension takes ownership of the variables it uses, and the elements must be `Send + 'static`
+
+```rust
+use better_comprehension::spawn_iter;
+
+let words = vec!["a".to_string(), "b".to_string()];
+let mut iter = spawn_iter![
+    format!("{w}{x}")
+    for x in 0..1_000_000 if x % 2 == 0
+    for w in words.iter()
+    with depth 4
+];
+assert_eq!(iter.next(), Some("a0".to_string()));
+assert_eq!(iter.next(), Some("b0".to_string()));
+assert_eq!(iter.next(), Some("a2".to_string()));
+// the producer stops here
+drop(iter);
+```
+
+# Async comprehensions
+`async_vector!` `async_vec_deque!` `async_linked_list!` `async_binary_heap!` `async_hash_set!` `async_b_tree_set!` `async_hash_map!` `async_b_tree_map!` evaluate to a future of the collection, so they work with any executor.
+
+`.await` can be used in the mapping, the iterables (the awaited value is iterated by value), the conditions and the `let` clauses.
+
+With `concurrently(n)`, the mapping is a future instead, and at most `n` of them are polled at the same time, using nothing but `std::future::Future` and `std::task::Waker`.
+The outputs are still put into the collection in input order.
+For maps the key is evaluated immediately and the value is the future.
+Since every mapping must be the same future type, `if ... else` is not supported in this mode, and futures that use the pattern variables should be `async move` blocks.
+
+```rust
+use better_comprehension::{async_hash_map, async_vector};
+# use std::future::Future;
+# use std::sync::Arc;
+# use std::task::{Context, Poll, Wake, Waker};
+# fn block_on<F: Future>(future: F) -> F::Output {
+#     struct ThreadWaker(std::thread::Thread);
+#     impl Wake for ThreadWaker {
+#         fn wake(self: Arc<Self>) {
+#             self.0.unpark();
+#         }
+#     }
+#     let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+#     let mut context = Context::from_waker(&waker);
+#     let mut future = std::pin::pin!(future);
+#     loop {
+#         match future.as_mut().poll(&mut context) {
+#             Poll::Ready(output) => return output,
+#             Poll::Pending => std::thread::park(),
+#         }
+#     }
+# }
+
+async fn load_ids() -> Vec<u32> {
+    vec![1, 2, 3, 4]
+}
+async fn is_active(id: u32) -> bool {
+    id != 3
+}
+async fn fetch_name(id: u32) -> String {
+    format!("user-{id}")
+}
+
+let names = block_on(async_vector![
+    fetch_name(id).await
+    for id in load_ids().await if is_active(id).await
+]);
+assert_eq!(names, vec!["user-1", "user-2", "user-4"]);
+
+let ids = [1, 2, 3, 4];
+let names = block_on(async_hash_map![
+    *id => fetch_name(*id)
+    for id in ids.iter()
+    concurrently(2)
+]);
+assert_eq!(names[&3], "user-3");
+```
+
+# Short-circuiting queries
+`any!` `all!` `find!` `first!` `position!` use the same clause syntax as the collection comprehensions,
+but they stop all the nested loops as soon as the answer is known,
+instead of building the whole cartesian product first
+
+any! :      bool, whether some element makes the mapping true
+
+all! :      bool, whether every element makes the mapping true
+
+first! :    Option of the first mapping produced
+
+find! :     Option of the first mapping whose predicate (`mapping if predicate`) is true
+
+position! : Option of the flat index (in iteration order, after filtering) of the first element making the mapping true
+
+```rust
+use better_comprehension::{all, any, find, first, position};
+let vec_1 = vec![1, 2, 3];
+let vec_2 = vec![4, 5, 6];
+
+let has_pair = any![
+    x + y == 8
+    for x in vec_1.iter()
+    for y in vec_2.iter()
+];
+assert!(has_pair);
+
+let all_positive = all![*x > 0 for x in vec_1.iter()];
+assert!(all_positive);
+
+// the loops stop at (2, 4)
+let pair = first![
+    (*x, *y)
+    for x in vec_1.iter() if x % 2 == 0
+    for y in vec_2.iter()
+];
+assert_eq!(pair, Some((2, 4)));
+
+let pair = find![
+    (*x, *y) if x * y > 10
+    for x in vec_1.iter()
+    for y in vec_2.iter()
+];
+assert_eq!(pair, Some((2, 6)));
+
+// (1,4) (1,5) (1,6) (2,4) ...
+let index = position![
+    x * y == 8
+    for x in vec_1.iter()
+    for y in vec_2.iter()
+];
+assert_eq!(index, Some(3));
+```
+
+# Fold and reduce
+`fold!` and `reduce!` thread an accumulator through the nested loops,
+so any reduction gets the same filtering, let binding and ownership handling as the collection comprehensions
+
+```ignore
+fold![init, |acc, item| body; mapping for ...]
+reduce![|acc, item| body; mapping for ...]
+```
+
+fold! :   returns the accumulator, starting from `init`
+
+reduce! : returns `Option`, the first mapping is the initial accumulator, `None` if nothing is produced
+
+A closure literal is expanded in place, so the parameter types are inferred from `init` and the mapping.
+Any other expression (such as `i32::max`) is called as a function
+
+```rust
+use better_comprehension::{fold, reduce};
+let words = vec!["hello".to_string(), "".to_string(), "world".to_string()];
+
+let sentence = fold![
+    String::new(), |acc, word| if acc.is_empty() { word } else { acc + " " + &word };
+    word.to_uppercase()
+    for word in words.iter() if !word.is_empty()
+];
+assert_eq!(sentence, "HELLO WORLD");
+
+let flags = [0b0001u8, 0b0100, 0b1000];
+let mask = fold![0u8, |acc, flag| acc | flag; *flag for flag in flags.iter()];
+assert_eq!(mask, 0b1101);
+
+let max_product = reduce![i32::max; x * y for x in 1..=3 for y in 1..=3 if x != y];
+assert_eq!(max_product, Some(6));
+
+let nothing = reduce![|a, b| a + b; x for x in 1..=3 if x > 3];
+assert_eq!(nothing, None);
+```
+
+# Statistical aggregations
+The mapping must be an `f64`, numeric conversions are written explicitly (e.g. `*x as f64`).
+Every macro returns `Option<f64>`, which is `None` for empty inputs
+
+mean! :                 arithmetic mean, single pass
+
+variance! / stddev! :   sample variance / standard deviation (divided by n-1), single pass (Welford), `None` with fewer than two elements
+
+median! :               median, buffers the elements
+
+percentile! :           `percentile![p; mapping for ...]` where `p` is in `0..=100`, linear interpolation between the closest ranks, buffers the elements
+
+```rust
+use better_comprehension::{mean, median, percentile, stddev, variance};
+let scores = vec![("Alice", 90), ("Bob", 70), ("Carol", 80), ("Dave", 0)];
+
+// absent students are filtered out
+let average = mean![*score as f64 for (_, score) in scores.iter() if *score > 0];
+assert_eq!(average, Some(80.0));
+
+let var = variance![*score as f64 for (_, score) in scores.iter() if *score > 0];
+assert_eq!(var, Some(100.0));
+let std = stddev![*score as f64 for (_, score) in scores.iter() if *score > 0];
+assert_eq!(std, Some(10.0));
+
+let mid = median![*score as f64 for (_, score) in scores.iter()];
+assert_eq!(mid, Some(75.0));
+let p90 = percentile![90.0; *score as f64 for (_, score) in scores.iter()];
+assert_eq!(p90, Some(87.0));
+
+let nothing = mean![*score as f64 for (_, score) in scores.iter() if *score > 100];
+assert_eq!(nothing, None);
+```
+
 # Iterator Comprehensions
 This library also supports iterator comprehensions, but as the author, I do not recommend using them, the reasons are as follows:
 1. In the collection comprehension, we also use references to derive, as long as we do not consume the original collection, we can achieve the  thing
@@ -419,9 +1168,34 @@ This library also supports iterator comprehensions, but as the author, I do not
 3. Because rust does not have a `yield` keyword, the implementation of iterator comprehension is complex, which leads to the inability to use  collection comprehension features
 
 The iterator comprehension is based on references, so it always does not consume ownership
-However, to ensure the correctness of the iterator comprehension, only two iterable objects are allowed to be passed in:
-* Single identifier (not followed by any method calls)
+However, to ensure the correctness of the iterator comprehension, every iterable must be able to be traversed again for each iteration of the outer loops:
+* Single identifier: the collection is borrowed and its references are collected once
 * Range expression (such as: 1..=3 or 1..x )
+* Reference, field or index (such as: &data.rows or self.items): borrowed and re-iterated
+* Any other expression (such as: data.iter().skip(1) or map.keys()) is evaluated once, and its iterator must implement `Clone`
+* An iterable that uses the variables of an outer pattern (such as: row.iter() in for row in grid) is evaluated again for each element
+* An iterable that borrows an outer `let` binding (such as: v.iter() after let v = ...) can not outlive it, so that level and the levels inside it are collected into a `Vec` for each element
+
+Literals, closures and control flow expressions are rejected with an error pointing at the iterable.
+
+```rust
+use better_comprehension::iterator_ref;
+struct Table {
+    rows: Vec<Vec<i32>>,
+    weights: Vec<i32>,
+}
+let table = Table {
+    rows: vec![vec![1, 2], vec![3]],
+    weights: vec![10, 20],
+};
+let weighted = iterator_ref![
+    cell * weight
+    for row in table.rows
+    for cell in row.iter()
+    for weight in table.weights.iter().skip(1)]
+.collect::<Vec<_>>();
+assert_eq!(weighted, vec![20, 40, 60]);
+```
 
 ```rust
 use better_comprehension::iterator_ref;
@@ -469,22 +1243,19 @@ let vec_2 = ["ABC".to_string(),
              "GHI".to_string()];
 
 let mut result3 = {
-    let vec_2 = vec_2.iter().collect::<Vec<_>>();
-    let vec_1 = vec_1.iter().collect::<Vec<_>>();
-    (vec_1).into_iter().filter_map(move |x| {
+    // only the references are moved into the closures, the elements are never copied
+    let vec_1 = &vec_1;
+    let vec_2 = &vec_2;
+    vec_1.iter().filter_map(move |x| {
         (x.contains("1") || x.contains("7")).then(|| {
-            let vec_2 = vec_2.clone();
-            (1..=2).into_iter().filter_map(move |_| {
-                (true).then(|| {
-                    let vec_2 = vec_2.clone();
-                    (vec_2).into_iter().filter_map(move |y| {
-                        (y.contains("D") || x.contains("3")).then(|| {
-                            if x.contains("1") {
-                                (x.clone(), y.clone())
-                            } else {
-                                (y.clone(), x.clone())
-                            }
-                        })
+            (1..=2).into_iter().map(move |_| {
+                vec_2.iter().filter_map(move |y| {
+                    (y.contains("D") || x.contains("3")).then(|| {
+                        if x.contains("1") {
+                            (x.clone(), y.clone())
+                        } else {
+                            (y.clone(), x.clone())
+                        }
                     })
                 })
             })
@@ -498,13 +1269,65 @@ This implementation makes the following features in collection comprehension una
 
 * if let expression
 
+The returned iterator is built from `map`, `filter_map` and `flatten` over `move` closures, so it keeps the abilities of the underlying iterators:
+* It is `Clone` when everything it captures is `Clone` (references always are)
+* A level without `if` uses `map`, so a single unfiltered level is an `ExactSizeIterator`
+* It is a `DoubleEndedIterator` when every iterable is, so `.rev()` works
+  (with `distinct`, `.rev()` keeps the last occurrence, see [Distinct](#distinct))
+
+```rust
+use better_comprehension::iterator_ref;
+let names = ["a".to_string(), "b".to_string(), "c".to_string()];
+
+let upper = iterator_ref![name.to_uppercase() for name in names];
+assert_eq!(upper.len(), 3);
+assert_eq!(upper.clone().rev().collect::<Vec<_>>(), vec!["C", "B", "A"]);
+assert_eq!(upper.collect::<Vec<_>>(), vec!["A", "B", "C"]);
+
+let pairs = iterator_ref![(i, name) for name in names if name != "b" for i in 0..2];
+assert_eq!(
+    pairs.rev().collect::<Vec<_>>(),
+    vec![(1, &names[2]), (0, &names[2]), (1, &names[0]), (0, &names[0])]
+);
+```
+
+# Owning iterator comprehensions
+`iterator_owned!` has the same syntax as `iterator_ref!` (mapping, conditions, `let` and `distinct`), but moves its input collections into the iterator instead of borrowing them.
+So it can be returned as `impl Iterator` from a function that consumes its arguments.
+
+* The outermost iterable is iterated by value and yields owned items
+* An inner iterable bound by an outer pattern (like `row` in `for row in grid for cell in row`) is moved as well
+* Any other inner collection is cloned for each iteration of the outer loops, so its items are clones
+* Any expression can be used as the outermost iterable. Inner iterables must be identifiers, ranges or expressions of outer-bound variables, other expressions (like `ys.iter()`, which would borrow the moved collection) are compile errors
+
+```rust
+use better_comprehension::iterator_owned;
+
+fn pairs(xs: Vec<String>, ys: Vec<String>) -> impl Iterator<Item = String> {
+    iterator_owned![format!("{x}{y}") for x in xs if !x.is_empty() for y in ys]
+}
+
+fn cells(grid: Vec<Vec<String>>) -> impl Iterator<Item = String> {
+    iterator_owned![cell for row in grid for cell in row if !cell.is_empty()]
+}
+
+let xs = vec!["a".to_string(), String::new(), "b".to_string()];
+let ys = vec!["1".to_string(), "2".to_string()];
+assert_eq!(pairs(xs, ys).collect::<Vec<_>>(), vec!["a1", "a2", "b1", "b2"]);
+
+let grid = vec![vec!["a".to_string(), String::new()], vec!["b".to_string()]];
+assert_eq!(cells(grid).collect::<Vec<_>>(), vec!["a", "b"]);
+```
+
 # Differences
 * Ownership consumption:
   * Collection comprehension:
     * Using & or .iter() does not consume ownership
     * Directly passing the variable name consumes ownership
   * Iterator comprehension:
-    * Always does not consume ownership, but only allows passing in a single identifier and range expression that does not follow any method s
+    * Always does not consume ownership, any iterable that can be traversed again (a collection, a reference or a `Clone` iterator) is allowed
+  * Owning iterator comprehension:
+    * Always consumes ownership, inner collections are cloned for each outer iteration
 
 * Differences in features:
   * if let expression
@@ -677,16 +1500,52 @@ assert_eq!(
 // #![allow(unused, dead_code)]
 use proc_macro::TokenStream as TS;
 
+// 在 to_tokens 中使用: 语法错误作为编译错误输出, 并结束本次展开
+macro_rules! ok_or_emit {
+    ($tokens:expr, $result:expr) => {
+        match $result {
+            Ok(value) => value,
+            Err(err) => {
+                $tokens.extend(syn::Error::into_compile_error(err));
+                return;
+            }
+        }
+    };
+}
+
 mod eager_evaluation;
-mod iter_clause;
+mod fold;
 mod lazy_evaluation;
-mod mapping;
+mod mutation;
+mod query;
+mod spawn;
+mod statistics;
+
+// 语法树, 解析与循环展开位于 better_comprehension_syntax 中, 本crate只负责各个宏的展开
+use better_comprehension_syntax::{
+    capacity_clause, iter_clause, mapping, parse_iter_clauses, sequence_clause,
+};
 
+pub(crate) use eager_evaluation::partition::SinkKind;
 pub(crate) use eager_evaluation::{
-    BTreeMapComprehension, BTreeSetComprehension, BinaryHeapComprehension, HashMapComprehension,
-    HashSetComprehension, LinkedListComprehension, VecComprehension, VecDequeComprehension,
+    AsyncComprehension, BTreeMapComprehension, BTreeSetComprehension, BinaryHeapComprehension,
+    CollectComprehension, ExtendComprehension, ExtendMapComprehension, HashMapComprehension,
+    HashSetComprehension, LinkedListComprehension, OptComprehension, ParallelComprehension,
+    PartitionComprehension, SinkComprehension, TryComprehension, UnzipComprehension,
+    ValidateComprehension, VecComprehension, VecDequeComprehension,
+};
+pub(crate) use fold::{FoldComprehension, ReduceComprehension};
+pub(crate) use lazy_evaluation::{IteratorOwned, IteratorRef};
+pub(crate) use mutation::{RetainComprehension, RetainMapComprehension, UpdateComprehension};
+pub(crate) use query::{
+    AllComprehension, AnyComprehension, FindComprehension, FirstComprehension,
+    PositionComprehension,
+};
+pub(crate) use spawn::SpawnIterComprehension;
+pub(crate) use statistics::{
+    MeanComprehension, MedianComprehension, PercentileComprehension, StdDevComprehension,
+    VarianceComprehension,
 };
-pub(crate) use lazy_evaluation::IteratorRef;
 
 #[proc_macro]
 pub fn vector(token_stream: TS) -> TS {
@@ -720,12 +1579,256 @@ pub fn hash_set(token_stream: TS) -> TS {
 pub fn hash_map(token_stream: TS) -> TS {
     process_comprehension::<HashMapComprehension>(token_stream)
 }
+#[proc_macro]
+pub fn collect(token_stream: TS) -> TS {
+    process_comprehension::<CollectComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn try_vector(token_stream: TS) -> TS {
+    process_comprehension::<TryComprehension<VecComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn try_binary_heap(token_stream: TS) -> TS {
+    process_comprehension::<TryComprehension<BinaryHeapComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn try_linked_list(token_stream: TS) -> TS {
+    process_comprehension::<TryComprehension<LinkedListComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn try_b_tree_set(token_stream: TS) -> TS {
+    process_comprehension::<TryComprehension<BTreeSetComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn try_b_tree_map(token_stream: TS) -> TS {
+    process_comprehension::<TryComprehension<BTreeMapComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn try_vec_deque(token_stream: TS) -> TS {
+    process_comprehension::<TryComprehension<VecDequeComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn try_hash_set(token_stream: TS) -> TS {
+    process_comprehension::<TryComprehension<HashSetComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn try_hash_map(token_stream: TS) -> TS {
+    process_comprehension::<TryComprehension<HashMapComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn try_collect(token_stream: TS) -> TS {
+    process_comprehension::<TryComprehension<CollectComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn opt_vector(token_stream: TS) -> TS {
+    process_comprehension::<OptComprehension<VecComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn opt_binary_heap(token_stream: TS) -> TS {
+    process_comprehension::<OptComprehension<BinaryHeapComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn opt_linked_list(token_stream: TS) -> TS {
+    process_comprehension::<OptComprehension<LinkedListComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn opt_b_tree_set(token_stream: TS) -> TS {
+    process_comprehension::<OptComprehension<BTreeSetComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn opt_b_tree_map(token_stream: TS) -> TS {
+    process_comprehension::<OptComprehension<BTreeMapComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn opt_vec_deque(token_stream: TS) -> TS {
+    process_comprehension::<OptComprehension<VecDequeComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn opt_hash_set(token_stream: TS) -> TS {
+    process_comprehension::<OptComprehension<HashSetComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn opt_hash_map(token_stream: TS) -> TS {
+    process_comprehension::<OptComprehension<HashMapComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn opt_collect(token_stream: TS) -> TS {
+    process_comprehension::<OptComprehension<CollectComprehension>>(token_stream)
+}
+#[proc_macro]
+pub fn par_vector(token_stream: TS) -> TS {
+    process_parallel(token_stream, SinkKind::Vector)
+}
+#[proc_macro]
+pub fn par_binary_heap(token_stream: TS) -> TS {
+    process_parallel(token_stream, SinkKind::BinaryHeap)
+}
+#[proc_macro]
+pub fn par_linked_list(token_stream: TS) -> TS {
+    process_parallel(token_stream, SinkKind::LinkedList)
+}
+#[proc_macro]
+pub fn par_b_tree_set(token_stream: TS) -> TS {
+    process_parallel(token_stream, SinkKind::BTreeSet)
+}
+#[proc_macro]
+pub fn par_b_tree_map(token_stream: TS) -> TS {
+    process_parallel(token_stream, SinkKind::BTreeMap)
+}
+#[proc_macro]
+pub fn par_vec_deque(token_stream: TS) -> TS {
+    process_parallel(token_stream, SinkKind::VecDeque)
+}
+#[proc_macro]
+pub fn par_hash_set(token_stream: TS) -> TS {
+    process_parallel(token_stream, SinkKind::HashSet)
+}
+#[proc_macro]
+pub fn par_hash_map(token_stream: TS) -> TS {
+    process_parallel(token_stream, SinkKind::HashMap)
+}
+#[proc_macro]
+pub fn async_vector(token_stream: TS) -> TS {
+    process_async(token_stream, SinkKind::Vector)
+}
+#[proc_macro]
+pub fn async_binary_heap(token_stream: TS) -> TS {
+    process_async(token_stream, SinkKind::BinaryHeap)
+}
+#[proc_macro]
+pub fn async_linked_list(token_stream: TS) -> TS {
+    process_async(token_stream, SinkKind::LinkedList)
+}
+#[proc_macro]
+pub fn async_b_tree_set(token_stream: TS) -> TS {
+    process_async(token_stream, SinkKind::BTreeSet)
+}
+#[proc_macro]
+pub fn async_b_tree_map(token_stream: TS) -> TS {
+    process_async(token_stream, SinkKind::BTreeMap)
+}
+#[proc_macro]
+pub fn async_vec_deque(token_stream: TS) -> TS {
+    process_async(token_stream, SinkKind::VecDeque)
+}
+#[proc_macro]
+pub fn async_hash_set(token_stream: TS) -> TS {
+    process_async(token_stream, SinkKind::HashSet)
+}
+#[proc_macro]
+pub fn async_hash_map(token_stream: TS) -> TS {
+    process_async(token_stream, SinkKind::HashMap)
+}
+#[proc_macro]
+pub fn validate(token_stream: TS) -> TS {
+    process_comprehension::<ValidateComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn extend(token_stream: TS) -> TS {
+    process_comprehension::<ExtendComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn extend_map(token_stream: TS) -> TS {
+    process_comprehension::<ExtendMapComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn partition(token_stream: TS) -> TS {
+    process_comprehension::<PartitionComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn unzip(token_stream: TS) -> TS {
+    process_comprehension::<UnzipComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn update(token_stream: TS) -> TS {
+    process_comprehension::<UpdateComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn retain(token_stream: TS) -> TS {
+    process_comprehension::<RetainComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn retain_map(token_stream: TS) -> TS {
+    process_comprehension::<RetainMapComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn sink(token_stream: TS) -> TS {
+    process_comprehension::<SinkComprehension>(token_stream)
+}
+#[proc_macro_derive(Comprehension, attributes(comprehension))]
+pub fn derive_comprehension(token_stream: TS) -> TS {
+    let input = syn::parse_macro_input!(token_stream as syn::DeriveInput);
+    eager_evaluation::sink::derive_comprehension(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
 #[proc_macro]
 pub fn iterator_ref(token_stream: TS) -> TS {
     process_comprehension::<IteratorRef>(token_stream)
 }
 
+#[proc_macro]
+pub fn iterator_owned(token_stream: TS) -> TS {
+    process_comprehension::<IteratorOwned>(token_stream)
+}
+
+#[proc_macro]
+pub fn spawn_iter(token_stream: TS) -> TS {
+    process_comprehension::<SpawnIterComprehension>(token_stream)
+}
+
+#[proc_macro]
+pub fn any(token_stream: TS) -> TS {
+    process_comprehension::<AnyComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn all(token_stream: TS) -> TS {
+    process_comprehension::<AllComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn find(token_stream: TS) -> TS {
+    process_comprehension::<FindComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn first(token_stream: TS) -> TS {
+    process_comprehension::<FirstComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn position(token_stream: TS) -> TS {
+    process_comprehension::<PositionComprehension>(token_stream)
+}
+
+#[proc_macro]
+pub fn fold(token_stream: TS) -> TS {
+    process_comprehension::<FoldComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn reduce(token_stream: TS) -> TS {
+    process_comprehension::<ReduceComprehension>(token_stream)
+}
+
+#[proc_macro]
+pub fn mean(token_stream: TS) -> TS {
+    process_comprehension::<MeanComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn variance(token_stream: TS) -> TS {
+    process_comprehension::<VarianceComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn stddev(token_stream: TS) -> TS {
+    process_comprehension::<StdDevComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn median(token_stream: TS) -> TS {
+    process_comprehension::<MedianComprehension>(token_stream)
+}
+#[proc_macro]
+pub fn percentile(token_stream: TS) -> TS {
+    process_comprehension::<PercentileComprehension>(token_stream)
+}
+
 fn process_comprehension<T>(token_stream: TS) -> TS
 where
     T: syn::parse::Parse + quote::ToTokens,
@@ -737,18 +1840,30 @@ where
     tokens.into()
 }
 
-pub(crate) fn common_parse(
-    input: syn::parse::ParseStream,
-) -> (crate::mapping::Mapping, Vec<iter_clause::IterClause>) {
-    let mut iter_clauses = Vec::new();
+// 并行推导式共用同一个结构, 由宏名决定目标集合
+fn process_parallel(token_stream: TS, sink: SinkKind) -> TS {
+    let parser =
+        |input: syn::parse::ParseStream| ParallelComprehension::parse_with_sink(input, sink);
+    let comprehension = syn::parse_macro_input!(token_stream with parser);
+    let tokens = quote::quote! {
+        #comprehension
+    };
+    tokens.into()
+}
 
-    let mapping = input
-        .parse::<crate::mapping::Mapping>()
-        .unwrap_or_else(|e| panic!("{}", e));
+fn process_async(token_stream: TS, sink: SinkKind) -> TS {
+    let parser = |input: syn::parse::ParseStream| AsyncComprehension::parse_with_sink(input, sink);
+    let comprehension = syn::parse_macro_input!(token_stream with parser);
+    let tokens = quote::quote! {
+        #comprehension
+    };
+    tokens.into()
+}
 
-    while let Ok(iter_clause) = input.parse::<iter_clause::IterClause>() {
-        iter_clauses.push(iter_clause);
-    }
+pub(crate) fn common_parse(
+    input: syn::parse::ParseStream,
+) -> syn::Result<(crate::mapping::Mapping, Vec<iter_clause::IterClause>)> {
+    let mapping = input.parse::<crate::mapping::Mapping>()?;
 
-    (mapping, iter_clauses)
+    Ok((mapping, parse_iter_clauses(input)))
 }
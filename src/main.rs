@@ -1,7 +1,15 @@
 #![allow(unused)]
 use better_comprehension::{
-    b_tree_map, b_tree_set, binary_heap, hash_map, hash_set, iterator_ref, linked_list, vec_deque,
-    vector,
+    all, any, async_b_tree_map, async_b_tree_set, async_binary_heap, async_hash_map,
+    async_hash_set, async_linked_list, async_vec_deque, async_vector, b_tree_map, b_tree_set,
+    binary_heap, collect, extend, extend_map, find, first, fold, hash_map, hash_set,
+    iterator_owned, iterator_ref, linked_list, mean, median, opt_b_tree_map, opt_b_tree_set,
+    opt_binary_heap, opt_collect, opt_hash_map, opt_hash_set, opt_linked_list, opt_vec_deque,
+    opt_vector, par_b_tree_map, par_b_tree_set, par_binary_heap, par_hash_map, par_hash_set,
+    par_linked_list, par_vec_deque, par_vector, partition, percentile, position, reduce, retain,
+    retain_map, spawn_iter, stddev, try_b_tree_map, try_b_tree_set, try_binary_heap, try_collect,
+    try_hash_map, try_hash_set, try_linked_list, try_vec_deque, try_vector, unzip, update,
+    validate, variance, vec_deque, vector,
 };
 use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet, LinkedList, VecDeque};
 
@@ -502,6 +510,1517 @@ fn some_real_example_2() {
     );
 }
 
+#[test]
+fn test_query() {
+    let vec_1 = [1, 2, 3];
+    let vec_2 = [4, 5, 6];
+
+    // 找到结果后立即停止所有循环
+    let mut visited = 0;
+    let found = any![
+        x + y == 6
+        for x in vec_1.iter()
+        for y in vec_2.iter()
+        let _ = visited += 1
+    ];
+    assert!(found);
+    assert_eq!(visited, 2);
+
+    let found = any![x > 10 for x in 1..=3];
+    assert!(!found);
+
+    let mut visited = 0;
+    let all_small = all![
+        *x < 2
+        for x in vec_1.iter()
+        let _ = visited += 1
+    ];
+    assert!(!all_small);
+    assert_eq!(visited, 2);
+    assert!(all![x > 0 for x in 1..=3]);
+
+    assert_eq!(first![x * 10 for x in vec_1.iter() if *x > 1], Some(20));
+    let empty: [i32; 0] = [];
+    assert_eq!(first![*x for x in empty], None);
+
+    // 带else的映射
+    assert_eq!(first![x if x > 5 else -x for x in 1..=3 if x > 1], Some(-2));
+
+    let people = [("Joe", 20), ("Bob", 25), ("Alice", 30)];
+    assert_eq!(
+        find![*name if *age > 20 for (name, age) in people.iter()],
+        Some("Bob")
+    );
+    assert_eq!(
+        find![*name if *age > 40 for (name, age) in people.iter()],
+        None
+    );
+
+    // position 返回过滤之后展平的下标
+    let index = position![
+        x + y == 8
+        for x in vec_1.iter() if *x != 1
+        for y in vec_2.iter()
+    ];
+    assert_eq!(index, Some(2));
+    assert_eq!(position![x == 0 for x in 1..=3], None);
+
+    // 嵌套的查询宏互不干扰
+    let nested = any![
+        all![*x < y for x in vec_1.iter()]
+        for y in vec_2.iter().copied()
+    ];
+    assert!(nested);
+
+    // 原集合可以被消耗
+    let vec_3 = vec![Some(1), None];
+    assert!(any![x.is_none() for x in vec_3]);
+}
+
+#[test]
+fn test_fold() {
+    let words = vec!["a".to_string(), "bb".to_string(), "ccc".to_string()];
+
+    // 拼接字符串
+    let joined = fold![
+        String::new(), |acc: String, w| acc + w;
+        w.as_str()
+        for w in words.iter() if w.len() > 1
+    ];
+    assert_eq!(joined, "bbccc");
+
+    // 带返回类型的闭包
+    let total = fold![0usize, |acc, len| -> usize { acc + len }; w.len() for w in words.iter()];
+    assert_eq!(total, 6);
+
+    // 函数作为fold函数, 且消耗原集合
+    let longest = fold![String::new(), std::cmp::max; w.clone() for w in words];
+    assert_eq!(longest, "ccc");
+
+    // 嵌套循环与let绑定
+    let sum = fold![
+        0, |acc, v| acc + v;
+        z
+        for x in 1..=3
+        for y in 1..=3 if x != y
+        let z = x * y
+    ];
+    assert_eq!(sum, 22);
+
+    // 自定义幺半群
+    #[derive(Debug, PartialEq)]
+    struct MinMax(i32, i32);
+    let values = [3, -1, 7, 2];
+    let min_max = reduce![
+        |MinMax(a, b), MinMax(c, d)| MinMax(a.min(c), b.max(d));
+        MinMax(x, x)
+        for x in values.iter().copied()
+    ];
+    assert_eq!(min_max, Some(MinMax(-1, 7)));
+
+    let empty = reduce![|a, b| a + b; x for x in 1..=3 if x > 3];
+    assert_eq!(empty, None);
+
+    // 闭包中的?与return只作用于闭包本身
+    let checked = fold![
+        Some(0u8), |acc: Option<u8>, x: u8| { let acc = acc?; acc.checked_add(x) };
+        x for x in ([100, 100, 100])
+    ];
+    assert_eq!(checked, None);
+    let clamped = fold![
+        0, |acc, x| { if x < 0 { return acc; } acc + x };
+        x for x in ([1, -5, 2])
+    ];
+    assert_eq!(clamped, 3);
+}
+
+#[test]
+fn test_statistics() {
+    // 两次遍历的参考实现
+    fn reference_mean(data: &[f64]) -> Option<f64> {
+        (!data.is_empty()).then(|| data.iter().sum::<f64>() / data.len() as f64)
+    }
+    fn reference_variance(data: &[f64], ddof: usize) -> Option<f64> {
+        let mean = reference_mean(data)?;
+        (data.len() > ddof).then(|| {
+            data.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (data.len() - ddof) as f64
+        })
+    }
+    fn reference_percentile(data: &[f64], p: f64) -> Option<f64> {
+        let mut sorted = data.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let rank = p / 100.0 * (sorted.len().checked_sub(1)?) as f64;
+        let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+        Some(sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64))
+    }
+    fn close(a: Option<f64>, b: Option<f64>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => (a - b).abs() <= 1e-9 * a.abs().max(1.0),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+
+    let matrix = [
+        vec![3, 1, 4, 1, 5],
+        vec![9, 2, 6, 5, 3],
+        vec![5, 8, 9, 7, 9],
+    ];
+    let flat = vector![
+        *x as f64
+        for row in matrix.iter()
+        for x in row.iter() if *x != 1
+    ];
+
+    let m = mean![*x as f64 for row in matrix.iter() for x in row.iter() if *x != 1];
+    assert!(close(m, reference_mean(&flat)));
+    let v = variance![*x as f64 for row in matrix.iter() for x in row.iter() if *x != 1];
+    assert!(close(v, reference_variance(&flat, 1)));
+    let sd = stddev![*x as f64 for row in matrix.iter() for x in row.iter() if *x != 1];
+    assert!(close(sd, reference_variance(&flat, 1).map(f64::sqrt)));
+
+    let med = median![*x as f64 for row in matrix.iter() for x in row.iter() if *x != 1];
+    assert!(close(med, reference_percentile(&flat, 50.0)));
+    for p in [0.0, 10.0, 25.0, 33.3, 75.0, 99.0, 100.0] {
+        let value =
+            percentile![p; *x as f64 for row in matrix.iter() for x in row.iter() if *x != 1];
+        assert!(close(value, reference_percentile(&flat, p)));
+    }
+
+    // 中位数: 奇数个与偶数个元素
+    assert_eq!(median![x as f64 for x in 1..=5], Some(3.0));
+    assert_eq!(median![x as f64 for x in 1..=4], Some(2.5));
+
+    // 数值稳定性: 大偏移量下的方差
+    let shifted = variance![1e9 + x as f64 for x in 1..=4];
+    assert!(close(shifted, Some(5.0 / 3.0)));
+
+    // 空输入与单个元素
+    assert_eq!(mean![x as f64 for x in 1..=3 if x > 3], None);
+    assert_eq!(median![x as f64 for x in 1..=3 if x > 3], None);
+    assert_eq!(variance![x as f64 for x in 1..=1], None);
+
+    // 调用处自定义的assert!不影响展开的代码
+    {
+        #[allow(unused_macros)]
+        macro_rules! assert {
+            ($($tokens:tt)*) => {
+                compile_error!("the expansion must not use the local assert!")
+            };
+        }
+        assert_eq!(percentile![50.0; x as f64 for x in 1..=3], Some(2.0));
+    }
+}
+
+#[test]
+fn test_order_by_take() {
+    #[derive(Debug, Clone, PartialEq)]
+    struct Record {
+        id: u32,
+        group: u8,
+        score: i64,
+    }
+    // 伪随机数据, 含大量重复的键
+    let records = vector![
+        Record { id, group: (id % 3) as u8, score: ((id * 7919) % 13) as i64 }
+        for id in 0..200u32
+    ];
+
+    // 参考实现: 稳定排序后截断
+    let mut expected = records.clone();
+    expected.sort_by_key(|r| (std::cmp::Reverse(r.score), r.group));
+
+    let sorted = vector![
+        r.clone()
+        for r in records.iter()
+        order by r.score desc, r.group
+    ];
+    assert_eq!(sorted, expected);
+
+    // 使用堆的top-k与完全排序结果一致, 包括相同键的顺序
+    for k in [0, 1, 5, 13, 199, 200, 500] {
+        let top_k = vec_deque![
+            r.clone()
+            for r in records.iter()
+            order by r.score desc, r.group
+            take k
+        ];
+        let expected_k: VecDeque<Record> = expected.iter().take(k).cloned().collect();
+        assert_eq!(top_k, expected_k);
+    }
+
+    // 嵌套循环, 使用内层绑定的变量作为键
+    let pairs = linked_list![
+        (x, y)
+        for x in 1..=3
+        for y in 1..=3 if x != y
+        let product = x * y
+        order by product desc, x
+        take 3
+    ];
+    assert_eq!(pairs, LinkedList::from([(2, 3), (3, 2), (1, 3)]));
+
+    // 只有take时提前停止
+    let mut visited = 0;
+    let taken = vector![
+        x
+        for x in 0..100
+        let _ = visited += 1
+        take 5
+    ];
+    assert_eq!(taken, vec![0, 1, 2, 3, 4]);
+    assert_eq!(visited, 5);
+    assert_eq!(vector![x for x in 0..100 take 0], Vec::<i32>::new());
+
+    // 带else的映射
+    let result = vector![x if x % 2 == 0 else -x for x in 1..=4 order by x desc];
+    assert_eq!(result, vec![4, -3, 2, -1]);
+}
+
+#[test]
+fn test_distinct() {
+    let data = [3, 1, 3, 2, 1, 5, 2];
+
+    let unique = vector![*x for x in data.iter() distinct];
+    assert_eq!(unique, vec![3, 1, 2, 5]);
+    let unique = vec_deque![*x for x in data.iter() distinct];
+    assert_eq!(unique, VecDeque::from([3, 1, 2, 5]));
+
+    // 键相同时保留第一次出现的元素, 且只对新键求值映射
+    let mut evaluated = 0;
+    let firsts = linked_list![
+        { evaluated += 1; (i, *x) }
+        for (i, x) in data.iter().enumerate()
+        distinct by *x
+    ];
+    assert_eq!(firsts, LinkedList::from([(0, 3), (1, 1), (3, 2), (5, 5)]));
+    assert_eq!(evaluated, 4);
+
+    // 嵌套循环与键值对元组作为键
+    let pairs = vector![
+        (x, y)
+        for x in 1..=3
+        for y in 1..=3
+        distinct by x.min(y) * 10 + x.max(y)
+    ];
+    assert_eq!(pairs, vec![(1, 1), (1, 2), (1, 3), (2, 2), (2, 3), (3, 3)]);
+
+    // 与order by和take组合
+    let result = vector![
+        *x
+        for x in data.iter()
+        distinct
+        order by std::cmp::Reverse(*x)
+        take 3
+    ];
+    assert_eq!(result, vec![5, 3, 2]);
+    let result = vector![*x for x in data.iter() distinct take 2];
+    assert_eq!(result, vec![3, 1]);
+
+    // 迭代器推导式
+    let words = ["apple", "avocado", "banana", "blueberry", "cherry"];
+    let firsts: Vec<_> = iterator_ref![
+        *word
+        for word in words
+        distinct by word.chars().next()
+    ]
+    .collect();
+    assert_eq!(firsts, vec!["apple", "banana", "cherry"]);
+    let letters: Vec<_> = iterator_ref![
+        word.len() % 3
+        for word in words
+        for _ in 1..=2
+        distinct
+    ]
+    .collect();
+    assert_eq!(letters, vec![2, 1, 0]);
+}
+
+#[test]
+fn test_join() {
+    #[derive(Debug, Clone, PartialEq)]
+    struct Employee {
+        name: &'static str,
+        dept: u32,
+        site: u32,
+    }
+    #[derive(Debug, Clone, PartialEq)]
+    struct Dept {
+        id: u32,
+        site: u32,
+        title: &'static str,
+    }
+    let employees = vec![
+        Employee {
+            name: "Ann",
+            dept: 1,
+            site: 1,
+        },
+        Employee {
+            name: "Bob",
+            dept: 2,
+            site: 1,
+        },
+        Employee {
+            name: "Cid",
+            dept: 1,
+            site: 2,
+        },
+        Employee {
+            name: "Dan",
+            dept: 3,
+            site: 1,
+        },
+    ];
+    let depts = vec![
+        Dept {
+            id: 1,
+            site: 1,
+            title: "R&D",
+        },
+        Dept {
+            id: 1,
+            site: 2,
+            title: "R&D remote",
+        },
+        Dept {
+            id: 2,
+            site: 1,
+            title: "Sales",
+        },
+        Dept {
+            id: 2,
+            site: 1,
+            title: "Sales (dup)",
+        },
+    ];
+
+    // 与嵌套循环的结果一致, 包括多个匹配的顺序
+    let nested = vector![
+        (e.name, d.title)
+        for e in &employees
+        for d in &depts if e.dept == d.id
+    ];
+    let joined = vector![
+        (e.name, d.title)
+        for e in &employees
+        join d in depts.iter() on e.dept == d.id
+    ];
+    assert_eq!(joined, nested);
+
+    // 多个键: && 与元组两种写法
+    let by_and = vector![
+        (e.name, d.title)
+        for e in &employees
+        join d in &depts on e.dept == d.id && e.site == d.site
+    ];
+    let by_tuple = vector![
+        (e.name, d.title)
+        for e in &employees
+        join d in &depts on (e.dept, e.site) == (d.id, d.site)
+    ];
+    assert_eq!(by_and, by_tuple);
+    assert_eq!(
+        by_and,
+        vec![
+            ("Ann", "R&D"),
+            ("Bob", "Sales"),
+            ("Bob", "Sales (dup)"),
+            ("Cid", "R&D remote")
+        ]
+    );
+
+    // left join, if与let作用于join之后
+    let titles = vector![
+        (e.name, title)
+        for e in &employees
+        left join d in &depts on (e.dept, e.site) == (d.id, d.site)
+        if e.name != "Bob"
+        let title = d.map(|d| d.title).unwrap_or("-")
+    ];
+    assert_eq!(
+        titles,
+        vec![("Ann", "R&D"), ("Cid", "R&D remote"), ("Dan", "-")]
+    );
+
+    // 连接多个集合, 后面的join可以使用前面join绑定的变量作为键
+    let sites = vec![(1, "Berlin"), (2, "Paris")];
+    let located = vector![
+        (e.name, d.title, *city)
+        for e in &employees
+        join d in &depts on e.dept == d.id
+        join (site_id, city) in &sites on d.site == *site_id
+        if e.dept == 1
+    ];
+    assert_eq!(
+        located,
+        vec![
+            ("Ann", "R&D", "Berlin"),
+            ("Ann", "R&D remote", "Paris"),
+            ("Cid", "R&D", "Berlin"),
+            ("Cid", "R&D remote", "Paris")
+        ]
+    );
+
+    // 被连接的集合可以被消耗, 范围也可以被连接
+    let squares = hash_map![
+        x => y
+        for x in 1..=5
+        join y in (1..=25) on x * x == y
+    ];
+    assert_eq!(squares.len(), 5);
+    let owned = depts.clone();
+    let count = vector![d.title for e in &employees join d in owned on e.dept == d.id].len();
+    assert_eq!(count, nested.len());
+}
+
+#[test]
+fn test_capacity() {
+    let data: Vec<u32> = (0..1000).collect();
+
+    // 单层且没有过滤时根据size_hint预分配, 且恰好一次分配
+    let vec = vector![x + 1 for x in data.iter()];
+    assert_eq!(vec.capacity(), data.len());
+    let vec_deque = vec_deque![x + 1 for x in data.iter()];
+    assert!(vec_deque.capacity() >= data.len());
+    let hash_map = hash_map![*x => x + 1 for x in data.iter()];
+    assert!(hash_map.capacity() >= data.len());
+    let hash_set = hash_set![x + 1 for x in 0..1000];
+    assert!(hash_set.capacity() >= 1000);
+    let binary_heap = binary_heap![*x for x in data.iter()];
+    assert!(binary_heap.capacity() >= data.len());
+
+    // 有take时不超过n
+    let vec = vector![*x for x in data.iter() take 10];
+    assert_eq!(vec.capacity(), 10);
+    let vec = vector![*x for x in data.iter() order by std::cmp::Reverse(*x) take 3];
+    assert_eq!(vec, vec![999, 998, 997]);
+    assert!(vec.capacity() < 10);
+
+    // 过滤或嵌套时不会根据size_hint预分配
+    let vec = vector![*x for x in data.iter() if *x < 2];
+    assert!(vec.capacity() < data.len());
+    let vec = vector![*x for x in data.iter() distinct by x % 2];
+    assert!(vec.capacity() < data.len());
+
+    // 显式给出容量
+    let vec = vector![*x for x in data.iter() if *x < 2 with capacity 64];
+    assert_eq!(vec, vec![0, 1]);
+    assert_eq!(vec.capacity(), 64);
+    let hash_map = hash_map![
+        (x, y) => x * y
+        for x in 0..10
+        for y in 0..10
+        with capacity 100
+    ];
+    assert_eq!(hash_map.len(), 100);
+    assert!(hash_map.capacity() >= 100);
+
+    // 预分配不影响所有权的处理
+    let owned = data.clone();
+    let vec = vector![x * 2 for x in owned];
+    assert_eq!(vec.len(), 1000);
+}
+
+#[test]
+fn test_pipeline() {
+    let data: Vec<i32> = (0..10).collect();
+
+    // 单层: map / filter_map
+    let vec = vector![x * 2 for x in data.iter()];
+    assert_eq!(vec, (0..10).map(|x| x * 2).collect::<Vec<_>>());
+    let vec = vector![*x if x % 2 == 0 else -x for x in data.iter() if *x < 5];
+    assert_eq!(vec, vec![0, -1, 2, -3, 4]);
+
+    // if let 的绑定可以在映射中使用
+    let options = [Some(1), None, Some(3)];
+    let vec = vector![x + 1 for option in options.iter() if let Some(x) = option];
+    assert_eq!(vec, vec![2, 4]);
+
+    // 多层嵌套: flat_map
+    let vec = vector![(x, y) for x in 0..3 if x != 1 for y in 0..x];
+    assert_eq!(vec, vec![(2, 0), (2, 1)]);
+    let strings = ["ab".to_string(), "c".to_string()];
+    let vec = vector![c for s in strings.iter() for c in (s.chars())];
+    assert_eq!(vec, vec!['a', 'b', 'c']);
+    let vec = vector![format!("{}{}", x, y) for x in 0..2 for y in 0..2];
+    assert_eq!(vec, vec!["00", "01", "10", "11"]);
+
+    // 内层引用外部的局部变量时回退到循环, 不会移动该变量
+    let prefix = String::from("n");
+    let vec = vector![format!("{}{}", prefix, x + y) for x in 0..2 for y in 0..2];
+    assert_eq!(vec, vec!["n0", "n1", "n1", "n2"]);
+    assert_eq!(prefix, "n");
+
+    // 映射可以修改外部状态
+    let mut count = 0;
+    let vec = vector![{ count += 1; *x } for x in data.iter() if *x > 6];
+    assert_eq!(vec, vec![7, 8, 9]);
+    assert_eq!(count, 3);
+
+    // 映射中的?作用于外层函数
+    fn parse_all(strings: &[&str]) -> Result<Vec<i32>, std::num::ParseIntError> {
+        Ok(vector![s.parse::<i32>()? for s in strings.iter()])
+    }
+    assert_eq!(parse_all(&["1", "2"]), Ok(vec![1, 2]));
+    assert!(parse_all(&["1", "x"]).is_err());
+
+    // 其他集合
+    let vec_deque = vec_deque![x for x in 0..3];
+    assert_eq!(vec_deque, VecDeque::from([0, 1, 2]));
+    let linked_list = linked_list![x for x in 0..3 if x > 0];
+    assert_eq!(linked_list, LinkedList::from([1, 2]));
+    let hash_set = hash_set![x % 3 for x in 0..10];
+    assert_eq!(hash_set, HashSet::from([0, 1, 2]));
+    // 重复的键以最后一个为准
+    let hash_map = hash_map![x % 3 => x for x in 0..10];
+    assert_eq!(hash_map, HashMap::from([(0, 9), (1, 7), (2, 8)]));
+    let hash_map = hash_map![x => "even" if x % 2 == 0 else x => "odd" for x in 0..3];
+    assert_eq!(
+        hash_map,
+        HashMap::from([(0, "even"), (1, "odd"), (2, "even")])
+    );
+
+    // 所有权的处理不变
+    let owned = vec!["a".to_string(), "b".to_string()];
+    let vec = vector![s.len() for s in owned];
+    assert_eq!(vec, vec![1, 1]);
+    let owned = ["a".to_string(), "b".to_string()];
+    let vec = vector![s.clone() for s in owned.iter() for _ in 0..2];
+    assert_eq!(vec, vec!["a", "a", "b", "b"]);
+    assert_eq!(owned.len(), 2);
+
+    // 内层借用或移动外层按值绑定的变量时回退到循环
+    fn make() -> Vec<String> {
+        vec!["ab".to_string(), "c".to_string()]
+    }
+    let vec = vector![c for s in (make()) for c in (s.chars())];
+    assert_eq!(vec, vec!['a', 'b', 'c']);
+    let vec = vector![s.clone() for s in (make()) for _ in 0..2 for _ in 0..1];
+    assert_eq!(vec, vec!["ab", "ab", "c", "c"]);
+    let set = b_tree_set![c for s in (make()) for c in (s.chars())];
+    assert_eq!(set, BTreeSet::from(['a', 'b', 'c']));
+    let mut vec = vec!['x'];
+    extend![vec; c for s in (make()) for c in (s.chars())];
+    assert_eq!(vec, vec!['x', 'a', 'b', 'c']);
+    let string: String = collect![String; c for s in (make()) for c in (s.chars())];
+    assert_eq!(string, "abc");
+}
+
+#[test]
+fn test_bulk_construction() {
+    // 相同的键以最后一个为准
+    let map = b_tree_map![x % 3 => x for x in 0..10];
+    assert_eq!(map, BTreeMap::from([(0, 9), (1, 7), (2, 8)]));
+    let pairs = [(1, "a"), (2, "b"), (1, "c")];
+    let map = b_tree_map![
+        k => v
+        for (k, v) in pairs.iter()
+        let v = v.to_uppercase()
+    ];
+    assert_eq!(map, BTreeMap::from([(&1, "C".to_string()), (&2, "B".to_string())]));
+
+    // 相等但可区分的元素保留第一个, 与逐个insert一致
+    #[derive(Debug, Clone, Copy)]
+    struct Tagged(i32, char);
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+    impl Eq for Tagged {}
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+    let items = [Tagged(2, 'a'), Tagged(1, 'b'), Tagged(2, 'c'), Tagged(1, 'd')];
+    let set = b_tree_set![*t for t in items.iter()];
+    let mut expected = BTreeSet::new();
+    for t in items.iter() {
+        expected.insert(*t);
+    }
+    assert_eq!(
+        set.iter().map(|t| t.1).collect::<Vec<_>>(),
+        expected.iter().map(|t| t.1).collect::<Vec<_>>()
+    );
+    assert_eq!(set.iter().map(|t| t.1).collect::<String>(), "ba");
+
+    // 堆的顺序与容量
+    let heap = binary_heap![x * y for x in 1..4 for y in 1..4 if x != y];
+    assert_eq!(heap.into_sorted_vec(), vec![2, 2, 3, 3, 6, 6]);
+    let heap = binary_heap![x for x in 0..10 if x % 2 == 0 with capacity 32];
+    assert!(heap.capacity() >= 32);
+    assert_eq!(heap.peek(), Some(&8));
+    let heap = binary_heap![y for x in 0..3 let y = x * 10];
+    assert_eq!(heap.into_vec().len(), 3);
+}
+
+#[test]
+fn test_collect() {
+    use std::borrow::Cow;
+    use std::rc::Rc;
+
+    let words = ["hello", "big", "world"];
+
+    // 任意实现了FromIterator的类型
+    let string =
+        collect![String; c.to_ascii_uppercase() for w in words.iter() for c in (w.chars())];
+    assert_eq!(string, "HELLOBIGWORLD");
+    let boxed = collect![Box<[usize]>; w.len() for w in words.iter()];
+    assert_eq!(&*boxed, &[5, 3, 5]);
+    let rc = collect![Rc<[usize]>; w.len() for w in words.iter() if w.len() > 3];
+    assert_eq!(&*rc, &[5, 5]);
+    let cow = collect![Cow<str>; *w for w in words.iter()];
+    assert_eq!(cow, "hellobigworld");
+
+    // 由上下文推断目标类型
+    let lengths: Vec<usize> = collect![w.len() for w in words.iter()];
+    assert_eq!(lengths, vec![5, 3, 5]);
+
+    // 键值对映射为 (键, 值)
+    let map: BTreeMap<&str, usize> = collect![*w => w.len() for w in words.iter()];
+    assert_eq!(map[&"big"], 3);
+    let map = collect![HashMap<_, _>; i => w for (i, w) in (words.iter().enumerate())];
+    assert_eq!(map[&2], &"world");
+
+    // Result在第一个Err处停止求值
+    let inputs = ["1", "x", "3"];
+    let mut visited = 0;
+    let result = collect![
+        Result<Vec<i32>, std::num::ParseIntError>;
+        { visited += 1; s.parse::<i32>() }
+        for s in inputs.iter()
+    ];
+    assert!(result.is_err());
+    assert_eq!(visited, 2);
+    let result =
+        collect![Option<Vec<i32>>; s.parse::<i32>().ok() for s in inputs.iter() if *s != "x"];
+    assert_eq!(result, Some(vec![1, 3]));
+
+    // 无法生成迭代器管道的形状同样在第一个Err/None处停止求值
+    let mut visited = 0;
+    let result = collect![
+        Result<Vec<i32>, std::num::ParseIntError>;
+        parsed for s in inputs.iter() let parsed = { visited += 1; s.parse::<i32>() }
+    ];
+    assert!(result.is_err());
+    assert_eq!(visited, 2);
+    let result = collect![Option<Vec<i32>>; n for s in inputs.iter() let n = s.parse::<i32>().ok()];
+    assert_eq!(result, None);
+    let result: Result<Vec<i32>, ()> = collect![
+        Result<_, _>;
+        Ok(n) for s in inputs.iter() if *s != "x" let n = s.parse::<i32>().unwrap()
+    ];
+    assert_eq!(result, Ok(vec![1, 3]));
+
+    // 无法生成迭代器管道的形状先缓存再构建
+    let string = collect![String; upper for w in words.iter() let upper = w.to_uppercase()];
+    assert_eq!(string, "HELLOBIGWORLD");
+
+    // 只实现了Default + Extend的类型(没有FromIterator)
+    #[derive(Default)]
+    struct Arena {
+        slots: Vec<usize>,
+        extends: usize,
+    }
+    impl Extend<usize> for Arena {
+        fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+            self.extends += 1;
+            self.slots.extend(iter);
+        }
+    }
+    let arena = collect![default Arena; w.len() for w in words.iter()];
+    assert_eq!(arena.slots, vec![5, 3, 5]);
+    assert_eq!(arena.extends, 1);
+    let arena = collect![default Arena; n for w in words.iter() if w.len() > 3 let n = w.len()];
+    assert_eq!(arena.slots, vec![5, 5]);
+
+    #[derive(Default)]
+    struct Index(Vec<(usize, char)>);
+    impl Extend<(usize, char)> for Index {
+        fn extend<I: IntoIterator<Item = (usize, char)>>(&mut self, iter: I) {
+            self.0.extend(iter);
+        }
+    }
+    let index = collect![default Index; i => c for (i, w) in (words.iter().enumerate()) for c in (w.chars().take(1))];
+    assert_eq!(index.0, vec![(0, 'h'), (1, 'b'), (2, 'w')]);
+}
+
+#[test]
+fn test_extend() {
+    let data = [3, 1, 2, 3];
+
+    // 每种集合使用各自的插入方式
+    let mut vec = vec![0];
+    extend![vec; x * 10 for x in data.iter() if *x > 1];
+    assert_eq!(vec, vec![0, 30, 20, 30]);
+    let mut vec_deque = VecDeque::from([0]);
+    extend![vec_deque; *x for x in data.iter()];
+    assert_eq!(vec_deque, VecDeque::from([0, 3, 1, 2, 3]));
+    let mut linked_list = LinkedList::new();
+    extend![linked_list; x for x in 0..3];
+    assert_eq!(linked_list, LinkedList::from([0, 1, 2]));
+    let mut hash_set = HashSet::from([9]);
+    extend![hash_set; *x for x in data.iter()];
+    assert_eq!(hash_set, HashSet::from([1, 2, 3, 9]));
+    let mut b_tree_set = BTreeSet::new();
+    extend![b_tree_set; x + y for x in 0..2 for y in 0..2];
+    assert_eq!(b_tree_set, BTreeSet::from([0, 1, 2]));
+    let mut binary_heap = BinaryHeap::from([5]);
+    extend![binary_heap; *x for x in data.iter()];
+    assert_eq!(binary_heap.into_sorted_vec(), vec![1, 2, 3, 3, 5]);
+    let mut string = String::from("ab");
+    extend![string; c for c in ('c'..='e')];
+    assert_eq!(string, "abcde");
+
+    // 键值对, 相同的键以最后一个为准
+    let mut config = HashMap::from([("a", 0), ("b", 0)]);
+    extend_map![config; *k => v for (k, v) in (["b", "c", "b"].iter().zip(1..))];
+    assert_eq!(config, HashMap::from([("a", 0), ("b", 3), ("c", 2)]));
+    let mut b_tree_map = BTreeMap::new();
+    extend_map![b_tree_map; x => y for x in 0..3 let y = x * x];
+    assert_eq!(b_tree_map, BTreeMap::from([(0, 0), (1, 1), (2, 4)]));
+
+    // 简单的形状根据size_hint预留容量
+    let mut vec: Vec<i32> = Vec::new();
+    extend![vec; *x for x in data.iter()];
+    assert_eq!(vec.capacity(), data.len());
+
+    // 目标是位置表达式, 只求值一次
+    struct Buffer {
+        items: Vec<i32>,
+    }
+    let mut buffer = Buffer { items: vec![] };
+    let mut evaluated = 0;
+    fn items<'a>(buffer: &'a mut Buffer, evaluated: &mut i32) -> &'a mut Vec<i32> {
+        *evaluated += 1;
+        &mut buffer.items
+    }
+    extend![*items(&mut buffer, &mut evaluated); x for x in 0..3 let x = x * 2];
+    assert_eq!(evaluated, 1);
+    assert_eq!(buffer.items, vec![0, 2, 4]);
+    let items = &mut buffer.items;
+    extend![*items; y for x in 0..2 for y in 0..x];
+    assert_eq!(buffer.items, vec![0, 2, 4, 0]);
+
+    // 在循环中追加
+    let mut lines = vec![];
+    for line in ["a b", "c"] {
+        extend![lines; word.to_string() for word in (line.split(' '))];
+    }
+    assert_eq!(lines, vec!["a", "b", "c"]);
+}
+
+#[test]
+fn test_update_retain() {
+    // update: 以iter_mut原地修改
+    let mut v = vec![-1, 2, 3];
+    update![*x += 1 for x in ref mut v if *x > 0];
+    assert_eq!(v, vec![-1, 3, 4]);
+    update![*x = 0 if *x < 0 else *x *= 10 for x in ref mut v];
+    assert_eq!(v, vec![0, 30, 40]);
+
+    // 嵌套: 修改内层集合的元素
+    let mut grid = vec![vec![1, 2], vec![3, 4]];
+    update![*y *= i for (i, row) in (grid.iter_mut().enumerate()) for y in ref mut row];
+    assert_eq!(grid, vec![vec![0, 0], vec![3, 4]]);
+    let mut scores = HashMap::from([("a", 1), ("b", 2)]);
+    update![*score += bonus for (_, score) in ref mut scores let bonus = 10];
+    assert_eq!(scores, HashMap::from([("a", 11), ("b", 12)]));
+
+    // retain
+    let mut v = vec![1, 2, 3, 4, 5, 6];
+    retain![v; x if x % 2 == 0];
+    assert_eq!(v, vec![2, 4, 6]);
+    let mut set = BTreeSet::from([1, 2, 3]);
+    retain![set; &x if x != 2];
+    assert_eq!(set, BTreeSet::from([1, 3]));
+    let mut options = VecDeque::from([Some(1), None, Some(5)]);
+    retain![options; x if let Some(n) = x && *n > 1];
+    assert_eq!(options, VecDeque::from([Some(5)]));
+
+    // retain_mut: 条件中可以修改元素
+    let mut v = vec![1, 2, 3];
+    retain![ref mut v; x if { *x *= 2; *x > 2 }];
+    assert_eq!(v, vec![4, 6]);
+
+    // 对内层的集合执行retain
+    let mut grid = vec![vec![1, -2, 3], vec![-4, 5]];
+    retain![*row; x if *x > 0 for row in ref mut grid];
+    assert_eq!(grid, vec![vec![1, 3], vec![5]]);
+
+    // retain_map: 值可以在条件中被修改
+    let mut stock = HashMap::from([("apple", 3), ("pear", 0), ("plum", 1)]);
+    retain_map![stock; name => count if { *count -= 1; *count > 0 || name.len() > 4 }];
+    assert_eq!(stock, HashMap::from([("apple", 2)]));
+    let mut inventory = [
+        BTreeMap::from([(1, "a"), (2, "b")]),
+        BTreeMap::from([(3, "c")]),
+    ];
+    retain_map![*map; &k, _ if k != 2 for map in ref mut inventory];
+    assert_eq!(inventory[0], BTreeMap::from([(1, "a")]));
+}
+
+#[test]
+fn test_partition_unzip() {
+    let data: [i32; 5] = [5, -3, 2, -8, 0];
+
+    // 两个分支放入不同的集合
+    let (positive, rest) = partition![(vector, vector); *x if *x > 0 else *x for x in data.iter()];
+    assert_eq!(positive, vec![5, 2]);
+    assert_eq!(rest, vec![-3, -8, 0]);
+
+    // 分支的值可以不同, 集合的种类也可以不同
+    let (small, large) = partition![
+        (b_tree_set, hash_map);
+        x.abs() if x.abs() < 5 else x => x * x
+        for x in data.iter()
+    ];
+    assert_eq!(small, BTreeSet::from([0, 2, 3]));
+    assert_eq!(large, HashMap::from([(&5, 25), (&-8, 64)]));
+    let (heap, list) =
+        partition![(binary_heap, linked_list); x if x % 2 == 0 else -x for x in 0..6];
+    assert_eq!(heap.into_sorted_vec(), vec![0, 2, 4]);
+    assert_eq!(list, LinkedList::from([-1, -3, -5]));
+
+    // unzip: 元组的每个元素放入对应的集合
+    let pairs = [("a", 1), ("b", 2), ("a", 3)];
+    let (names, numbers) = unzip![(vector, hash_set); (*name, n % 2) for (name, n) in pairs.iter()];
+    assert_eq!(names, vec!["a", "b", "a"]);
+    assert_eq!(numbers, HashSet::from([0, 1]));
+
+    // 多于两个集合, 键值对集合的元素为 (键, 值)
+    let (deque, index, last) = unzip![
+        (vec_deque, b_tree_map, hash_map);
+        (n, (i, *name), (*name, n))
+        for (i, (name, n)) in (pairs.iter().enumerate())
+    ];
+    assert_eq!(deque, VecDeque::from([&1, &2, &3]));
+    assert_eq!(index[&2], "a");
+    assert_eq!(last, HashMap::from([("a", &3), ("b", &2)]));
+
+    // 与其他子句组合
+    let (even, odd) = partition![
+        (vector, vector);
+        y if y % 2 == 0 else y
+        for x in 0..3
+        for y in x..3 if y > 0
+        let y = y * 10 + x
+    ];
+    assert_eq!(even, vec![10, 20, 22]);
+    assert_eq!(odd, vec![11, 21]);
+}
+
+#[test]
+fn test_try() {
+    use std::num::ParseIntError;
+
+    // 映射中的`?`只从推导式中返回
+    let inputs = ["1", "2", "3"];
+    let parsed: Result<Vec<i32>, ParseIntError> =
+        try_vector![x.parse::<i32>()? * 2 for x in inputs.iter()];
+    assert_eq!(parsed, Ok(vec![2, 4, 6]));
+
+    let inputs = ["1", "x", "3"];
+    let parsed: Result<Vec<i32>, ParseIntError> =
+        try_vector![x.parse::<i32>()? for x in inputs.iter()];
+    assert!(parsed.is_err());
+
+    // `in?` 逐个解包, 在第一个Err处停止
+    let results: [Result<i32, String>; 3] = [Ok(1), Ok(2), Ok(3)];
+    let set: Result<BTreeSet<i32>, String> = try_b_tree_set![x for x in? results if x > 1];
+    assert_eq!(set, Ok(BTreeSet::from([2, 3])));
+
+    let mut visited = 0;
+    let results: [Result<i32, String>; 3] = [Ok(1), Err("bad".to_string()), Ok(3)];
+    let list: Result<LinkedList<i32>, String> = try_linked_list![{
+        visited += 1;
+        x
+    } for x in? results];
+    assert_eq!(list, Err("bad".to_string()));
+    assert_eq!(visited, 1);
+
+    // 错误经由From转换, 条件与let子句中同样可以使用`?`
+    #[derive(Debug, PartialEq)]
+    enum ConfigError {
+        Parse,
+        Missing(String),
+    }
+    impl From<ParseIntError> for ConfigError {
+        fn from(_: ParseIntError) -> Self {
+            ConfigError::Parse
+        }
+    }
+    fn lookup(key: &str) -> Result<&'static str, ConfigError> {
+        match key {
+            "port" => Ok("8080"),
+            "workers" => Ok("4"),
+            "debug" => Ok("x"),
+            _ => Err(ConfigError::Missing(key.to_string())),
+        }
+    }
+
+    let keys = ["port", "workers"];
+    let config: Result<HashMap<&str, u32>, ConfigError> = try_hash_map![
+        *key => value
+        for key in keys.iter()
+        let value = lookup(key)?.parse::<u32>()?
+    ];
+    assert_eq!(config, Ok(HashMap::from([("port", 8080), ("workers", 4)])));
+
+    let keys = ["port", "debug"];
+    let config: Result<HashMap<&str, u32>, ConfigError> = try_hash_map![
+        *key => lookup(key)?.parse::<u32>()?
+        for key in keys.iter()
+    ];
+    assert_eq!(config, Err(ConfigError::Parse));
+
+    let keys = ["port", "host"];
+    let config: Result<Vec<&str>, ConfigError> =
+        try_vector![*key for key in keys.iter() if !lookup(key)?.is_empty()];
+    assert_eq!(config, Err(ConfigError::Missing("host".to_string())));
+
+    // 多层嵌套与其他集合
+    let rows = [vec!["1", "2"], vec!["3"]];
+    let deque: Result<VecDeque<i32>, ParseIntError> =
+        try_vec_deque![cell.parse::<i32>()? for row in rows.iter() for cell in row.iter()];
+    assert_eq!(deque, Ok(VecDeque::from([1, 2, 3])));
+    let heap: Result<BinaryHeap<i32>, ParseIntError> =
+        try_binary_heap![cell.parse::<i32>()? for row in rows.iter() for cell in row.iter()];
+    assert_eq!(heap.unwrap().into_sorted_vec(), vec![1, 2, 3]);
+    let map: Result<BTreeMap<usize, i32>, ParseIntError> = try_b_tree_map![
+        i => cell.parse::<i32>()?
+        for (i, row) in (rows.iter().enumerate())
+        for cell in row.iter()
+    ];
+    assert_eq!(map, Ok(BTreeMap::from([(0, 2), (1, 3)])));
+    let set: Result<HashSet<i32>, ParseIntError> =
+        try_hash_set![x.parse::<i32>()? % 2 for row in rows.iter() for x in row.iter()];
+    assert_eq!(set, Ok(HashSet::from([0, 1])));
+    let chars: [Result<char, ()>; 2] = [Ok('a'), Ok('b')];
+    let text: Result<String, ()> = try_collect![String; c.to_ascii_uppercase() for c in? chars];
+    assert_eq!(text, Ok("AB".to_string()));
+
+    // 不带try_的推导式中, `in?` 从所在的函数返回
+    fn sum_all(results: Vec<Result<i32, String>>) -> Result<i32, String> {
+        let values = vector![x for x in? results];
+        Ok(values.iter().sum())
+    }
+    assert_eq!(sum_all(vec![Ok(1), Ok(2)]), Ok(3));
+    assert_eq!(
+        sum_all(vec![Ok(1), Err("e".to_string())]),
+        Err("e".to_string())
+    );
+}
+
+#[test]
+fn test_validate() {
+    use std::num::ParseIntError;
+
+    // 全部为Ok时得到集合
+    let inputs = ["1", "2", "3"];
+    let parsed: Result<Vec<i32>, Vec<ParseIntError>> =
+        validate![vector; x.parse::<i32>() for x in inputs.iter()];
+    assert_eq!(parsed, Ok(vec![1, 2, 3]));
+
+    // 收集每一个错误, 而不是第一个
+    let inputs = ["1", "x", "3", "y"];
+    let parsed = validate![b_tree_set; x.parse::<i32>() for x in inputs.iter()];
+    assert_eq!(parsed.unwrap_err().len(), 2);
+
+    // 每个元素都被访问
+    let mut visited = 0;
+    let checked = validate![vector; {
+        visited += 1;
+        if x % 2 == 0 { Ok(x) } else { Err(x) }
+    } for x in 0..6];
+    assert_eq!(checked, Err(vec![1, 3, 5]));
+    assert_eq!(visited, 6);
+
+    // with context 为错误附加模式变量
+    let form = [("name", ""), ("age", "x"), ("city", "Paris")];
+    let errors = validate![
+        hash_map with context;
+        if value.is_empty() { Err("empty") } else { Ok((*field, *value)) }
+        for (field, value) in form.iter()
+    ];
+    assert_eq!(errors, Err(vec![((&"name", &""), "empty")]));
+
+    let rows = [vec!["1", "2"], vec!["a", "4"]];
+    let table: Result<BTreeMap<(usize, usize), i32>, _> = validate![
+        b_tree_map with context;
+        cell.parse::<i32>().map(|value| ((i, j), value))
+        for (i, row) in (rows.iter().enumerate())
+        for (j, cell) in (row.iter().enumerate())
+    ];
+    let errors = table.unwrap_err();
+    assert_eq!(errors.len(), 1);
+    let ((i, _, j, cell), _) = &errors[0];
+    assert_eq!((*i, *j, *cell), (1, 0, &"a"));
+
+    // 其他集合与if/else映射
+    let numbers = [4, 5, -1];
+    let set: Result<HashSet<i32>, Vec<String>> = validate![
+        hash_set;
+        Ok(x / 2) if *x >= 0 else Err(format!("{x} is negative"))
+        for x in numbers.iter()
+    ];
+    assert_eq!(set, Err(vec!["-1 is negative".to_string()]));
+    let list: Result<LinkedList<i32>, Vec<()>> = validate![linked_list; Ok(x) for x in 0..3];
+    assert_eq!(list, Ok(LinkedList::from([0, 1, 2])));
+    let heap: Result<BinaryHeap<i32>, Vec<()>> = validate![binary_heap; Ok(x) for x in 0..3];
+    assert_eq!(heap.unwrap().into_sorted_vec(), vec![0, 1, 2]);
+    let deque: Result<VecDeque<i32>, Vec<()>> = validate![vec_deque; Ok(x) for x in 0..3];
+    assert_eq!(deque, Ok(VecDeque::from([0, 1, 2])));
+}
+
+#[test]
+fn test_opt() {
+    let prices = HashMap::from([("apple", 3), ("pear", 5), ("plum", 2)]);
+
+    // 全部存在时得到集合
+    let keys = ["apple", "pear"];
+    let found = opt_vector![*prices.get(k)? for k in keys.iter()];
+    assert_eq!(found, Some(vec![3, 5]));
+
+    // 任何一个缺失时为None, 之后的元素不再求值
+    let mut visited = 0;
+    let keys = ["apple", "kiwi", "pear"];
+    let found = opt_vector![{
+        visited += 1;
+        *prices.get(k)?
+    } for k in keys.iter()];
+    assert_eq!(found, None);
+    assert_eq!(visited, 2);
+
+    // if let 仍然跳过缺失的元素
+    let found = vector![*price for k in keys.iter() if let Some(price) = prices.get(k)];
+    assert_eq!(found, vec![3, 5]);
+
+    // 条件, let子句与 `in?` 中同样可以使用`?`
+    let orders = [("apple", 2), ("plum", 4)];
+    let totals = opt_hash_map![
+        *name => total
+        for (name, count) in orders.iter() if prices.get(name)? % 2 == 1
+        let total = prices.get(name)? * count
+    ];
+    assert_eq!(totals, Some(HashMap::from([("apple", 6)])));
+    let options = [Some(1), Some(2), Some(3)];
+    let set = opt_b_tree_set![x * 10 for x in? options];
+    assert_eq!(set, Some(BTreeSet::from([10, 20, 30])));
+    let options = [Some(1), None, Some(3)];
+    let set = opt_hash_set![x for x in? options];
+    assert_eq!(set, None);
+
+    // 其他集合
+    let names = ["plum", "apple"];
+    let deque = opt_vec_deque![*prices.get(k)? for k in names.iter()];
+    assert_eq!(deque, Some(VecDeque::from([2, 3])));
+    let list = opt_linked_list![*prices.get(k)? for k in names.iter()];
+    assert_eq!(list, Some(LinkedList::from([2, 3])));
+    let heap = opt_binary_heap![*prices.get(k)? for k in names.iter()];
+    assert_eq!(heap.unwrap().into_sorted_vec(), vec![2, 3]);
+    let map = opt_b_tree_map![*k => *prices.get(k)? for k in names.iter()];
+    assert_eq!(map, Some(BTreeMap::from([("apple", 3), ("plum", 2)])));
+    let text = opt_collect![String; char::from_digit(*prices.get(k)?, 10)? for k in names.iter()];
+    assert_eq!(text, Some("23".to_string()));
+}
+
+#[test]
+fn test_parallel() {
+    let numbers: Vec<i32> = (0..1000).collect();
+
+    // 结果与顺序执行的推导式相同
+    let expected = vector![x * 2 for x in numbers.iter() if x % 3 == 0];
+    let result = par_vector![x * 2 for x in numbers.iter() if x % 3 == 0];
+    assert_eq!(result, expected);
+
+    // 多层嵌套, 内层的变量在所有线程间共享
+    let words = ["a", "b", "c"];
+    let expected = vector![format!("{x}{w}") for x in 0..50 for w in words.iter() if x % 7 != 0];
+    let result = par_vector![
+        format!("{x}{w}")
+        for x in 0..50
+        for w in words.iter() if x % 7 != 0
+        with threads 3
+        with chunk 4
+    ];
+    assert_eq!(result, expected);
+
+    // 映射中后出现的键覆盖先出现的键
+    let expected = hash_map![x % 10 => x for x in numbers.iter()];
+    let result = par_hash_map![x % 10 => x for x in numbers.iter() with threads 8 with chunk 1];
+    assert_eq!(result, expected);
+    let result = par_b_tree_map![x % 10 => x for x in numbers.iter() with chunk 7];
+    assert_eq!(result, b_tree_map![x % 10 => x for x in numbers.iter()]);
+
+    // 其他集合
+    assert_eq!(
+        par_hash_set![x % 17 for x in numbers.iter()],
+        hash_set![x % 17 for x in numbers.iter()]
+    );
+    assert_eq!(
+        par_b_tree_set![x / 9 for x in numbers.iter() with threads 2],
+        b_tree_set![x / 9 for x in numbers.iter()]
+    );
+    assert_eq!(
+        par_vec_deque![x for x in 0..100 with chunk 3],
+        vec_deque![x for x in 0..100]
+    );
+    assert_eq!(
+        par_linked_list![x for x in 0..100 with threads 16],
+        linked_list![x for x in 0..100]
+    );
+    assert_eq!(
+        par_binary_heap![x for x in 0..100].into_sorted_vec(),
+        binary_heap![x for x in 0..100].into_sorted_vec()
+    );
+
+    // 变量按照与顺序执行相同的方式被移动
+    let owned = vec!["x".to_string(), "y".to_string()];
+    let result = par_vector![s.clone() + "!" for s in owned];
+    assert_eq!(result, vec!["x!", "y!"]);
+
+    // join与let子句, 空的可迭代对象
+    let scores = [("a", 1), ("b", 2)];
+    let names = ["a", "b", "c"];
+    let result = par_vector![
+        (name, doubled)
+        for name in names.iter()
+        join (key, score) in scores.iter() on *name == *key
+        let doubled = score * 2
+        with threads 2
+    ];
+    assert_eq!(result, vec![(&"a", 2), (&"b", 4)]);
+    let empty: Vec<i32> = vec![];
+    let result: Vec<&i32> = par_vector![x for x in empty.iter()];
+    assert!(result.is_empty());
+}
+
+#[test]
+fn test_spawn_iter() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // 结果与顺序执行的推导式相同
+    let words = ["a".to_string(), "b".to_string()];
+    let result: Vec<String> = spawn_iter![
+        format!("{w}{x}")
+        for x in 0..3 if x != 1
+        for w in words.iter()
+    ]
+    .collect();
+    assert_eq!(result, vec!["a0", "b0", "a2", "b2"]);
+
+    // 键值对产生 (键, 值) 元组
+    let map: HashMap<i32, i32> = spawn_iter![x => x * x for x in 0..4 with depth 1].collect();
+    assert_eq!(map, HashMap::from([(0, 0), (1, 1), (2, 4), (3, 9)]));
+
+    // 消费者drop迭代器后, 生产者提前停止
+    let produced = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&produced);
+    let mut iter = spawn_iter![{
+        counter.fetch_add(1, Ordering::SeqCst);
+        x
+    } for x in 0..1_000_000 with depth 0];
+    assert_eq!(iter.next(), Some(0));
+    assert_eq!(iter.next(), Some(1));
+    drop(iter);
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    assert!(produced.load(Ordering::SeqCst) < 10);
+
+    // 生产者panic时, 消费者在迭代器耗尽处panic
+    let result = std::panic::catch_unwind(|| {
+        spawn_iter![if x < 2 { x } else { panic!("producer failed") } for x in 0..5]
+            .collect::<Vec<_>>()
+    });
+    assert!(result.is_err());
+}
+
+// 测试用的最小执行器: 在当前线程上轮询, Pending时park直到被唤醒
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct ThreadWaker(std::thread::Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park(),
+        }
+    }
+}
+
+// 返回n次Pending(每次都立即唤醒)后完成
+async fn yield_times(n: usize) {
+    let mut remaining = n;
+    std::future::poll_fn(|context| match remaining {
+        0 => std::task::Poll::Ready(()),
+        _ => {
+            remaining -= 1;
+            context.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    })
+    .await
+}
+
+#[test]
+fn test_async() {
+    use std::cell::Cell;
+
+    async fn numbers(n: i32) -> Vec<i32> {
+        yield_times(1).await;
+        (0..n).collect()
+    }
+    async fn is_even(x: i32) -> bool {
+        yield_times(2).await;
+        x % 2 == 0
+    }
+    async fn double(x: i32) -> i32 {
+        yield_times(1).await;
+        x * 2
+    }
+
+    // 可迭代对象, 条件, let子句与映射中都可以使用await
+    let result = block_on(async_vector![
+        double(y).await
+        for x in numbers(6).await if is_even(x).await
+        let y = x + 1
+    ]);
+    assert_eq!(result, vec![2, 6, 10]);
+
+    let ids = [3, 1, 2];
+    let map = block_on(async_b_tree_map![*x => double(*x).await for x in ids.iter()]);
+    assert_eq!(map, BTreeMap::from([(1, 2), (2, 4), (3, 6)]));
+
+    // concurrently(n): 最多同时轮询n个Future, 结果按输入的顺序排列
+    let active = Cell::new(0);
+    let max_active = Cell::new(0);
+    let track = |x: i32| {
+        let (active, max_active) = (&active, &max_active);
+        async move {
+            active.set(active.get() + 1);
+            max_active.set(max_active.get().max(active.get()));
+            // 后面的元素先完成
+            yield_times(10 - x as usize).await;
+            active.set(active.get() - 1);
+            x * 10
+        }
+    };
+    let result = block_on(async_vector![track(x) for x in 0..10 concurrently(3)]);
+    assert_eq!(result, vec![0, 10, 20, 30, 40, 50, 60, 70, 80, 90]);
+    assert_eq!(max_active.get(), 3);
+
+    max_active.set(0);
+    let result = block_on(async_vec_deque![track(x) for x in 0..4 if x != 2 concurrently(1)]);
+    assert_eq!(result, VecDeque::from([0, 10, 30]));
+    assert_eq!(max_active.get(), 1);
+
+    // 键值对的键立即求值, 值为Future; 后出现的键覆盖先出现的键
+    let map = block_on(async_hash_map![x % 3 => track(x) for x in 0..7 concurrently(4)]);
+    assert_eq!(map, HashMap::from([(0, 60), (1, 40), (2, 50)]));
+
+    // 其他集合
+    let words = ["b", "a", "b"];
+    let set = block_on(async_b_tree_set![
+        async move { w.to_uppercase() }
+        for w in words.iter()
+        concurrently(2)
+    ]);
+    assert_eq!(set, BTreeSet::from(["A".to_string(), "B".to_string()]));
+    let set = block_on(async_hash_set![double(*x).await for x in ids.iter()]);
+    assert_eq!(set, HashSet::from([2, 4, 6]));
+    let list = block_on(async_linked_list![double(x) for x in 0..3 concurrently(8)]);
+    assert_eq!(list, LinkedList::from([0, 2, 4]));
+    let heap = block_on(async_binary_heap![double(x).await for x in 0..3]);
+    assert_eq!(heap.into_sorted_vec(), vec![0, 2, 4]);
+}
+
+#[test]
+fn test_iterator_owned() {
+    // 从消耗其参数的函数中返回
+    fn pairs(xs: Vec<String>, ys: Vec<String>) -> impl Iterator<Item = String> {
+        iterator_owned![format!("{x}{y}") for x in xs if !x.is_empty() for y in ys]
+    }
+    let xs = vec!["a".to_string(), String::new(), "b".to_string()];
+    let ys = vec!["1".to_string(), "2".to_string()];
+    let result: Vec<String> = pairs(xs, ys).collect();
+    assert_eq!(result, vec!["a1", "a2", "b1", "b2"]);
+
+    // 外层模式绑定的变量直接移动, 产生拥有所有权的元素
+    fn cells(grid: Vec<Vec<String>>) -> impl Iterator<Item = String> {
+        iterator_owned![cell for row in grid for cell in row if !cell.is_empty()]
+    }
+    let grid = vec![
+        vec!["a".to_string(), String::new()],
+        vec!["b".to_string(), "c".to_string()],
+    ];
+    assert_eq!(cells(grid).collect::<Vec<_>>(), vec!["a", "b", "c"]);
+
+    // let子句, if/else映射, 范围与distinct
+    fn labels(names: Vec<&'static str>, limit: i32) -> impl Iterator<Item = String> {
+        iterator_owned![
+            format!("{name}{n}") if n % 2 == 0 else name.to_uppercase()
+            for n in 0..limit
+            for name in names
+            let name = name.repeat(2)
+        ]
+    }
+    let result: Vec<String> = labels(vec!["a", "b"], 2).collect();
+    assert_eq!(result, vec!["aa0", "bb0", "AA", "BB"]);
+    let numbers = vec![3, 1, 3, 2, 1];
+    let unique: Vec<i32> = iterator_owned![x * 10 for x in numbers distinct].collect();
+    assert_eq!(unique, vec![30, 10, 20]);
+
+    // 内层的范围可以使用被捕获的变量
+    fn labeled(n: usize, names: Vec<char>) -> impl Iterator<Item = (char, usize)> {
+        iterator_owned![(name, i) for name in names for i in (0..n)]
+    }
+    let result: Vec<(char, usize)> = labeled(2, vec!['a', 'b']).collect();
+    assert_eq!(result, vec![('a', 0), ('a', 1), ('b', 0), ('b', 1)]);
+
+    // 惰性求值
+    let evens = iterator_owned![x for x in (0..) if x % 2 == 0];
+    assert_eq!(evens.take(3).collect::<Vec<_>>(), vec![0, 2, 4]);
+}
+
+#[test]
+fn test_iterator_ref_expressions() {
+    struct Inventory {
+        items: Vec<String>,
+        counts: BTreeMap<String, i32>,
+    }
+    impl Inventory {
+        // 字段按引用遍历
+        fn labels(&self) -> impl Iterator<Item = String> + '_ {
+            iterator_ref![
+                format!("{item}:{n}")
+                for item in self.items
+                for n in self.counts.values() if *n > 1
+            ]
+        }
+    }
+    let inventory = Inventory {
+        items: vec!["a".to_string(), "b".to_string()],
+        counts: BTreeMap::from([("x".to_string(), 1), ("y".to_string(), 3)]),
+    };
+    assert_eq!(inventory.labels().collect::<Vec<_>>(), vec!["a:3", "b:3"]);
+    assert_eq!(inventory.items.len(), 2);
+
+    // 方法调用产生的可克隆迭代器与切片
+    let word = "ab".to_string();
+    let numbers = [1, 2, 3, 4];
+    let result: Vec<String> = iterator_ref![
+        format!("{c}{n}")
+        for c in word.chars()
+        for n in &numbers[2..]
+    ]
+    .collect();
+    assert_eq!(result, vec!["a3", "a4", "b3", "b4"]);
+
+    let scores = HashMap::from([("a", 1)]);
+    let keys: Vec<_> = iterator_ref![(*k, i) for i in 0..2 for k in scores.keys()].collect();
+    assert_eq!(keys, vec![("a", 0), ("a", 1)]);
+
+    // 使用外层变量的可迭代对象在每次迭代中重新求值
+    let grid = [vec![1, 2], vec![3]];
+    let cells: Vec<i32> = iterator_ref![*x * 10 for row in grid for x in row.iter()].collect();
+    assert_eq!(cells, vec![10, 20, 30]);
+    let cells: Vec<i32> = iterator_ref![*x for row in grid for x in row if *x != 2].collect();
+    assert_eq!(cells, vec![1, 3]);
+    let pairs: Vec<(i32, i32)> = iterator_ref![(i, j) for i in 0..3 for j in (0..i)].collect();
+    assert_eq!(pairs, vec![(1, 0), (2, 0), (2, 1)]);
+    let values: Vec<i32> =
+        iterator_ref![*y for x in 0..3 let v = [x, x + 1] for y in v.iter()].collect();
+    assert_eq!(values, vec![0, 1, 1, 2, 2, 3]);
+    // 借用let绑定的层在闭包中求值, 更内层的循环同样有效
+    let values: Vec<i32> = iterator_ref![
+        y * 10 + z
+        for x in 0..2 let v = [x, x + 1]
+        for y in v.iter().rev() if *y > 0
+        for z in 0..2
+    ]
+    .collect();
+    assert_eq!(values, vec![10, 11, 20, 21, 10, 11]);
+    let values: Vec<i32> =
+        iterator_ref![y for x in 1..3 let v = vec![x; x as usize] for y in v].collect();
+    assert_eq!(values, vec![1, 2, 2]);
+    assert_eq!(grid.len(), 2);
+}
+
+#[test]
+fn test_iterator_ref_borrows() {
+    // 参数本身是引用, 同一个集合在多层中使用, 映射中也可以使用集合的名字
+    fn cross<'a>(
+        names: &'a Vec<String>,
+        suffixes: &'a [&str],
+    ) -> impl Iterator<Item = String> + 'a {
+        iterator_ref![
+            format!("{a}{b}{s}/{}", names.len())
+            for a in names
+            for b in names if a != b
+            for s in suffixes
+        ]
+    }
+    let names = vec!["x".to_string(), "y".to_string()];
+    let result = cross(&names, &["!"]).collect::<Vec<_>>();
+    assert_eq!(result, vec!["xy!/2", "yx!/2"]);
+
+    // 元素是对原集合的引用, 而不是副本
+    let data = [String::from("a"), String::from("b")];
+    let first = iterator_ref![x for _ in 0..2 for x in data].next().unwrap();
+    assert!(std::ptr::eq(first, &data[0]));
+}
+
+#[test]
+fn test_iterator_ref_adapters() {
+    let data = [3, 1, 4, 1, 5];
+    let words = ["x", "y"];
+
+    // 单层且没有条件时为ExactSizeIterator
+    let doubled = iterator_ref![x * 2 for x in data];
+    assert_eq!(doubled.size_hint(), (5, Some(5)));
+    assert_eq!(doubled.len(), 5);
+
+    // Clone与DoubleEndedIterator
+    let copy = doubled.clone();
+    assert_eq!(doubled.rev().collect::<Vec<_>>(), vec![10, 2, 8, 2, 6]);
+    assert_eq!(copy.collect::<Vec<_>>(), vec![6, 2, 8, 2, 10]);
+
+    let mut evens = iterator_ref![x for x in data if x % 2 == 0];
+    assert_eq!(evens.size_hint(), (0, Some(5)));
+    assert_eq!(evens.next_back(), Some(&4));
+
+    // 多层
+    let pairs = iterator_ref![(*x, *w) for x in data if *x > 3 for w in words];
+    assert_eq!(
+        pairs.clone().rev().collect::<Vec<_>>(),
+        vec![(5, "y"), (5, "x"), (4, "y"), (4, "x")]
+    );
+    assert_eq!(pairs.count(), 4);
+
+    // let子句, distinct与可克隆的迭代器表达式
+    let unique = iterator_ref![y for x in data.iter().skip(1) let y = x + 1 distinct];
+    assert_eq!(unique.clone().collect::<Vec<_>>(), vec![2, 5, 6]);
+    assert_eq!(unique.rev().collect::<Vec<_>>(), vec![6, 2, 5]);
+}
+
 fn test() {
     let branch_1 = 1;
     let branch_2 = 2; // 我修改了过去, 为branch_2添加了注释
@@ -0,0 +1,245 @@
+use crate::iter_clause::IterClause;
+use crate::mapping::Mapping;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Expr;
+use syn::Token;
+use syn::parse::ParseStream;
+
+// 原地修改集合, 不产生新的集合
+// `for x in ref mut collection` 以iter_mut遍历(见ForInClause)
+
+// 条件可能是 `let` 链, 因此写成if表达式
+fn keep_tokens(conditions: &Expr) -> TokenStream {
+    quote! {
+        if #conditions {
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/*-----------------UpdateComprehension------------------- */
+// update![*x += 1 for x in ref mut v if *x > 0]
+// 映射作为语句对每个元素执行一次
+#[derive(Debug)]
+pub struct UpdateComprehension {
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for UpdateComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let UpdateComprehension {
+            mapping,
+            iter_clauses,
+        } = self;
+
+        let value = ok_or_emit!(tokens, mapping.value_tokens("update!"));
+        let nested_code = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_nested_loops(iter_clauses, quote! { #value; })
+        );
+
+        tokens.extend(quote! {
+            {
+                #nested_code
+            }
+        });
+    }
+}
+
+impl syn::parse::Parse for UpdateComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+        if iter_clauses.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &mapping.left_key,
+                "update! needs at least one for-in clause",
+            ));
+        }
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+/*-----------------RetainComprehension------------------- */
+// retain![v; x if keep(x)] 展开为 v.retain(..)
+// retain![ref mut v; x if ...] 展开为 v.retain_mut(..), 元素可以在条件中被修改
+// 之后可以跟随for子句, 对内层的集合执行retain: retain![*row; x if ... for row in ref mut grid]
+#[derive(Debug)]
+pub struct RetainComprehension {
+    pub by_ref_mut: bool,
+    pub target: Expr,
+    pub pat: syn::Pat,
+    pub conditions: Expr,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for RetainComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let RetainComprehension {
+            by_ref_mut,
+            target,
+            pat,
+            conditions,
+            iter_clauses,
+        } = self;
+
+        let method = match by_ref_mut {
+            true => quote! { retain_mut },
+            false => quote! { retain },
+        };
+        let keep = keep_tokens(conditions);
+        let nested_code = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_nested_loops(
+                iter_clauses,
+                quote! {
+                    (#target).#method(|#pat| #keep);
+                },
+            )
+        );
+
+        tokens.extend(quote! {
+            {
+                #nested_code
+            }
+        });
+    }
+}
+
+impl syn::parse::Parse for RetainComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let by_ref_mut = input.peek(Token![ref]) && input.peek2(Token![mut]);
+        if by_ref_mut {
+            input.parse::<Token![ref]>()?;
+            input.parse::<Token![mut]>()?;
+        }
+        let target = input.parse::<Expr>()?;
+        input.parse::<Token![;]>()?;
+
+        let pat = input.call(syn::Pat::parse_single)?;
+        input.parse::<Token![if]>()?;
+        let conditions = input.parse::<Expr>()?;
+
+        Ok(Self {
+            by_ref_mut,
+            target,
+            pat,
+            conditions,
+            iter_clauses: crate::parse_iter_clauses(input),
+        })
+    }
+}
+
+/*-----------------RetainMapComprehension------------------- */
+// retain_map![m; k => v if ...] 展开为 m.retain(|k, v| ..), 值可以在条件中被修改
+#[derive(Debug)]
+pub struct RetainMapComprehension {
+    pub target: Expr,
+    pub key_pat: syn::Pat,
+    pub value_pat: syn::Pat,
+    pub conditions: Expr,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for RetainMapComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let RetainMapComprehension {
+            target,
+            key_pat,
+            value_pat,
+            conditions,
+            iter_clauses,
+        } = self;
+
+        let keep = keep_tokens(conditions);
+        let nested_code = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_nested_loops(
+                iter_clauses,
+                quote! {
+                    (#target).retain(|#key_pat, #value_pat| #keep);
+                },
+            )
+        );
+
+        tokens.extend(quote! {
+            {
+                #nested_code
+            }
+        });
+    }
+}
+
+impl syn::parse::Parse for RetainMapComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let target = input.parse::<Expr>()?;
+        input.parse::<Token![;]>()?;
+
+        let key_pat = input.call(syn::Pat::parse_single)?;
+        // 与映射相同, 接受 `=>` `,` `:` 作为键值对分隔符
+        if input.peek(Token![=>]) {
+            input.parse::<Token![=>]>()?;
+        } else if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        } else {
+            input.parse::<Token![:]>()?;
+        }
+        let value_pat = input.call(syn::Pat::parse_single)?;
+        input.parse::<Token![if]>()?;
+        let conditions = input.parse::<Expr>()?;
+
+        Ok(Self {
+            target,
+            key_pat,
+            value_pat,
+            conditions,
+            iter_clauses: crate::parse_iter_clauses(input),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_mutation_parse() {
+        // 测试update
+        let update: UpdateComprehension = parse_quote! {
+            *x += 1 for x in ref mut v if *x > 0
+        };
+        assert!(matches!(update.mapping.left_key, Expr::Binary(_)));
+        assert_eq!(update.iter_clauses.len(), 1);
+        eprintln!("UpdateComprehension测试通过");
+
+        let error = syn::parse2::<UpdateComprehension>(quote! { *x += 1 }).unwrap_err();
+        assert!(error.to_string().contains("at least one for-in clause"));
+        eprintln!("UpdateComprehension缺少for子句测试通过");
+
+        // 测试retain与嵌套的for子句
+        let retain: RetainComprehension = parse_quote! {
+            ref mut *row; x if *x > 0 for row in ref mut grid
+        };
+        assert!(retain.by_ref_mut);
+        assert!(matches!(retain.target, Expr::Unary(_)));
+        assert_eq!(retain.iter_clauses.len(), 1);
+        eprintln!("RetainComprehension测试通过");
+
+        // 测试retain_map
+        let retain_map: RetainMapComprehension = parse_quote! {
+            m; k => v if let Some(x) = v && k.len() > *x
+        };
+        assert!(matches!(retain_map.key_pat, syn::Pat::Ident(_)));
+        assert!(matches!(retain_map.conditions, Expr::Binary(_)));
+        assert!(retain_map.iter_clauses.is_empty());
+        eprintln!("RetainMapComprehension测试通过");
+    }
+}
@@ -0,0 +1,279 @@
+use crate::iter_clause::IterClause;
+use crate::mapping::Mapping;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::Expr;
+use syn::Token;
+use syn::parse::ParseStream;
+
+// 短路查询使用带标签的块, 找到结果后直接跳出所有嵌套循环
+// 标签使用mixed_site, 以免与用户代码或嵌套的查询宏冲突
+fn query_label() -> syn::Lifetime {
+    syn::Lifetime::new("'__query_comprehension", Span::mixed_site())
+}
+
+fn wrap_query(
+    iter_clauses: &[IterClause],
+    nested_code: TokenStream,
+    default: TokenStream,
+) -> syn::Result<TokenStream> {
+    let label = query_label();
+    let nested_code = crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code)?;
+
+    Ok(quote! {
+        {
+            #label: {
+                #nested_code
+                #default
+            }
+        }
+    })
+}
+
+/*-----------------AnyComprehension------------------- */
+#[derive(Debug)]
+pub struct AnyComprehension {
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for AnyComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let predicate = ok_or_emit!(tokens, self.mapping.value_tokens("any!"));
+        let label = query_label();
+
+        let nested_code = quote! {
+            if #predicate {
+                break #label true;
+            }
+        };
+
+        let nested_code = ok_or_emit!(
+            tokens,
+            wrap_query(&self.iter_clauses, nested_code, quote! { false },)
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for AnyComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+/*-----------------AllComprehension------------------- */
+#[derive(Debug)]
+pub struct AllComprehension {
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for AllComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let predicate = ok_or_emit!(tokens, self.mapping.value_tokens("all!"));
+        let label = query_label();
+
+        let nested_code = quote! {
+            if !(#predicate) {
+                break #label false;
+            }
+        };
+
+        let nested_code = ok_or_emit!(
+            tokens,
+            wrap_query(&self.iter_clauses, nested_code, quote! { true })
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for AllComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+/*-----------------FirstComprehension------------------- */
+#[derive(Debug)]
+pub struct FirstComprehension {
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for FirstComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let value = ok_or_emit!(tokens, self.mapping.value_tokens("first!"));
+        let label = query_label();
+
+        let nested_code = quote! {
+            break #label ::std::option::Option::Some(#value);
+        };
+
+        let nested_code = ok_or_emit!(
+            tokens,
+            wrap_query(
+                &self.iter_clauses,
+                nested_code,
+                quote! { ::std::option::Option::None },
+            )
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for FirstComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+/*-----------------FindComprehension------------------- */
+// find! 的映射形如 `value if predicate`, 没有else分支
+#[derive(Debug)]
+pub struct FindComprehension {
+    pub value: Expr,
+    pub predicate: Option<Expr>,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for FindComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let FindComprehension {
+            value,
+            predicate,
+            iter_clauses,
+        } = self;
+        let label = query_label();
+
+        let nested_code = match predicate {
+            Some(predicate) => quote! {
+                if #predicate {
+                    break #label ::std::option::Option::Some(#value);
+                }
+            },
+            None => quote! {
+                break #label ::std::option::Option::Some(#value);
+            },
+        };
+
+        let nested_code = ok_or_emit!(
+            tokens,
+            wrap_query(
+                iter_clauses,
+                nested_code,
+                quote! { ::std::option::Option::None },
+            )
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for FindComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let value = input.parse::<Expr>()?;
+
+        let mut predicate = None;
+        if input.peek(Token![if]) {
+            input.parse::<Token![if]>()?;
+            predicate = Some(input.parse::<Expr>()?);
+        }
+
+        Ok(Self {
+            value,
+            predicate,
+            iter_clauses: crate::parse_iter_clauses(input),
+        })
+    }
+}
+
+/*-----------------PositionComprehension------------------- */
+// 返回第一个满足条件的元素在展平后的迭代顺序中的下标
+#[derive(Debug)]
+pub struct PositionComprehension {
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for PositionComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let predicate = ok_or_emit!(tokens, self.mapping.value_tokens("position!"));
+        let label = query_label();
+
+        let nested_code = quote! {
+            if #predicate {
+                break #label ::std::option::Option::Some(__position_comprehension);
+            }
+            __position_comprehension += 1;
+        };
+
+        let nested_code = ok_or_emit!(
+            tokens,
+            wrap_query(
+                &self.iter_clauses,
+                nested_code,
+                quote! { ::std::option::Option::None },
+            )
+        );
+
+        tokens.extend(quote! {
+            {
+                let mut __position_comprehension: usize = 0;
+                #nested_code
+            }
+        });
+    }
+}
+
+impl syn::parse::Parse for PositionComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_find_parse() {
+        // 测试带谓词的find解析
+        let find: FindComprehension = parse_quote! {
+            x.name.clone() if x.age > 20 for x in people
+        };
+        assert!(matches!(find.value, Expr::MethodCall(_)));
+        assert!(matches!(find.predicate, Some(Expr::Binary(_))));
+        assert_eq!(find.iter_clauses.len(), 1);
+        eprintln!("FindComprehension带谓词测试通过");
+
+        // 测试不带谓词的find解析
+        let find: FindComprehension = parse_quote! {
+            (x, y) for x in 1..3 for y in 1..3 if x + y == 3
+        };
+        assert!(matches!(find.value, Expr::Tuple(_)));
+        assert!(find.predicate.is_none());
+        assert_eq!(find.iter_clauses.len(), 2);
+        eprintln!("FindComprehension不带谓词测试通过");
+    }
+}
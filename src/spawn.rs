@@ -0,0 +1,138 @@
+use crate::iter_clause::IterClause;
+use crate::mapping::Mapping;
+use quote::quote;
+use syn::Expr;
+use syn::parse::ParseStream;
+
+mod keyword {
+    syn::custom_keyword!(with);
+    syn::custom_keyword!(depth);
+}
+
+/*-----------------SpawnIterComprehension------------------- */
+// spawn_iter![mapping for ... with depth n]
+// 在新线程中执行嵌套循环, 元素经由容量为n的sync_channel传出, 返回从Receiver读取的迭代器
+// 迭代器被drop后发送失败, 生产者在下一个元素处停止; 生产者panic时在迭代器耗尽处重新panic
+#[derive(Debug)]
+pub struct SpawnIterComprehension {
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+    pub depth: Option<Expr>,
+}
+
+impl quote::ToTokens for SpawnIterComprehension {
+    fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
+        let SpawnIterComprehension {
+            mapping,
+            iter_clauses,
+            depth,
+        } = self;
+
+        // 键值对的映射产生 (键, 值) 元组
+        let item = ok_or_emit!(
+            tokens,
+            match mapping.left_value {
+                Some(_) => mapping.pair_tokens(),
+                None => mapping.value_tokens("spawn_iter!"),
+            }
+        );
+        let depth = match depth {
+            Some(depth) => quote! { #depth },
+            None => quote! { 16 },
+        };
+
+        let nested_code = ok_or_emit!(
+            tokens,
+            crate::eager_evaluation::handle_nested_loops(
+                iter_clauses,
+                quote! {
+                    if __spawn_sender.send(#item).is_err() {
+                        return;
+                    }
+                },
+            )
+        );
+
+        tokens.extend(quote! {
+            {
+                let (__spawn_sender, __spawn_receiver) = ::std::sync::mpsc::sync_channel(#depth);
+                let mut __spawn_handle = ::std::option::Option::Some(::std::thread::spawn(
+                    move || {
+                        #nested_code
+                    },
+                ));
+                ::std::iter::from_fn(move || match __spawn_receiver.recv() {
+                    ::std::result::Result::Ok(__spawn_item) => {
+                        ::std::option::Option::Some(__spawn_item)
+                    }
+                    ::std::result::Result::Err(_) => {
+                        if let ::std::option::Option::Some(__spawn_handle) = __spawn_handle.take() {
+                            if let ::std::result::Result::Err(__spawn_panic) = __spawn_handle.join() {
+                                ::std::panic::resume_unwind(__spawn_panic);
+                            }
+                        }
+                        ::std::option::Option::None
+                    }
+                })
+            }
+        });
+    }
+}
+
+impl syn::parse::Parse for SpawnIterComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+        if let Some(iter_clause) = iter_clauses
+            .iter()
+            .find(|iter_clause| iter_clause.for_in_clause.fallible)
+        {
+            return Err(syn::Error::new_spanned(
+                &iter_clause.for_in_clause.iterable,
+                "`in?` is not supported for spawn_iter!",
+            ));
+        }
+        let depth = match input.peek(keyword::with) {
+            true => {
+                input.parse::<keyword::with>()?;
+                input.parse::<keyword::depth>()?;
+                Some(input.parse::<Expr>()?)
+            }
+            false => None,
+        };
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+            depth,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_spawn_iter_parse() {
+        let spawn_iter: SpawnIterComprehension = parse_quote! {
+            x * 2 for x in items if x > 0 with depth 4
+        };
+        assert_eq!(spawn_iter.iter_clauses.len(), 1);
+        assert!(matches!(spawn_iter.depth, Some(Expr::Lit(_))));
+        eprintln!("SpawnIterComprehension测试通过");
+
+        let spawn_iter: SpawnIterComprehension = parse_quote! {
+            (x, y) for x in 0..3 for y in 0..x
+        };
+        assert_eq!(spawn_iter.iter_clauses.len(), 2);
+        assert!(spawn_iter.depth.is_none());
+        eprintln!("SpawnIterComprehension默认容量测试通过");
+
+        // `in?` 报告为指向可迭代对象的错误
+        let error =
+            syn::parse2::<SpawnIterComprehension>(quote! { x for x in? results }).unwrap_err();
+        assert!(error.to_string().contains("`in?`"));
+        eprintln!("SpawnIterComprehension in?测试通过");
+    }
+}
@@ -0,0 +1,279 @@
+use crate::iter_clause::IterClause;
+use crate::mapping::Mapping;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Expr;
+use syn::Token;
+use syn::parse::ParseStream;
+
+// 映射必须是f64, 数值转换需要用户显式写出(如 `*x as f64`)
+// 均值与方差使用Welford算法单次遍历, 中位数与百分位数需要缓存所有元素
+
+fn welford_tokens(
+    name: &str,
+    mapping: &Mapping,
+    iter_clauses: &[IterClause],
+    with_m2: bool,
+    finish: TokenStream,
+) -> syn::Result<TokenStream> {
+    let value = mapping.value_tokens(name)?;
+
+    let update_m2 = match with_m2 {
+        true => {
+            quote! { __statistics_m2 += __statistics_delta * (__statistics_item - __statistics_mean); }
+        }
+        false => quote! {},
+    };
+    let declare_m2 = match with_m2 {
+        true => quote! { let mut __statistics_m2: f64 = 0.0; },
+        false => quote! {},
+    };
+
+    let mut nested_code = quote! {
+        let __statistics_item: f64 = #value;
+        __statistics_count += 1;
+        let __statistics_delta = __statistics_item - __statistics_mean;
+        __statistics_mean += __statistics_delta / __statistics_count as f64;
+        #update_m2
+    };
+
+    nested_code = crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code)?;
+    Ok(quote! {
+        {
+            let mut __statistics_count: usize = 0;
+            let mut __statistics_mean: f64 = 0.0;
+            #declare_m2
+            #nested_code
+            #finish
+        }
+    })
+}
+
+fn sample_variance() -> TokenStream {
+    quote! {
+        (__statistics_count >= 2).then(|| __statistics_m2 / (__statistics_count - 1) as f64)
+    }
+}
+
+// 线性插值的百分位数, 与numpy的默认方式一致
+fn percentile_tokens(
+    name: &str,
+    percentile: TokenStream,
+    mapping: &Mapping,
+    iter_clauses: &[IterClause],
+) -> syn::Result<TokenStream> {
+    let value = mapping.value_tokens(name)?;
+
+    let mut nested_code = quote! {
+        let __statistics_item: f64 = #value;
+        __statistics_buffer.push(__statistics_item);
+    };
+
+    nested_code = crate::eager_evaluation::handle_nested_loops(iter_clauses, nested_code)?;
+    Ok(quote! {
+        {
+            let __statistics_percentile: f64 = #percentile;
+            ::core::assert!(
+                (0.0..=100.0).contains(&__statistics_percentile),
+                "percentile must be in 0..=100, got {}",
+                __statistics_percentile
+            );
+            let mut __statistics_buffer: ::std::vec::Vec<f64> = ::std::vec::Vec::new();
+            #nested_code
+            match __statistics_buffer.len() {
+                0 => ::std::option::Option::None,
+                __statistics_len => {
+                    __statistics_buffer.sort_by(f64::total_cmp);
+                    let __statistics_rank =
+                        __statistics_percentile / 100.0 * (__statistics_len - 1) as f64;
+                    let __statistics_lower = __statistics_rank.floor() as usize;
+                    let __statistics_upper = __statistics_rank.ceil() as usize;
+                    let __statistics_low = __statistics_buffer[__statistics_lower];
+                    let __statistics_high = __statistics_buffer[__statistics_upper];
+                    ::std::option::Option::Some(
+                        __statistics_low
+                            + (__statistics_high - __statistics_low)
+                                * (__statistics_rank - __statistics_lower as f64),
+                    )
+                }
+            }
+        }
+    })
+}
+
+/*-----------------MeanComprehension------------------- */
+#[derive(Debug)]
+pub struct MeanComprehension {
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for MeanComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let nested_code = ok_or_emit!(
+            tokens,
+            welford_tokens(
+                "mean!",
+                &self.mapping,
+                &self.iter_clauses,
+                false,
+                quote! { (__statistics_count >= 1).then_some(__statistics_mean) },
+            )
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for MeanComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+/*-----------------VarianceComprehension------------------- */
+// 样本方差(分母为n-1), 少于两个元素时为None
+#[derive(Debug)]
+pub struct VarianceComprehension {
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for VarianceComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let nested_code = ok_or_emit!(
+            tokens,
+            welford_tokens(
+                "variance!",
+                &self.mapping,
+                &self.iter_clauses,
+                true,
+                sample_variance(),
+            )
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for VarianceComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+/*-----------------StdDevComprehension------------------- */
+#[derive(Debug)]
+pub struct StdDevComprehension {
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for StdDevComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let variance = sample_variance();
+        let nested_code = ok_or_emit!(
+            tokens,
+            welford_tokens(
+                "stddev!",
+                &self.mapping,
+                &self.iter_clauses,
+                true,
+                quote! { (#variance).map(f64::sqrt) },
+            )
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for StdDevComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+/*-----------------MedianComprehension------------------- */
+#[derive(Debug)]
+pub struct MedianComprehension {
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for MedianComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let nested_code = ok_or_emit!(
+            tokens,
+            percentile_tokens(
+                "median!",
+                quote! { 50.0 },
+                &self.mapping,
+                &self.iter_clauses,
+            )
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for MedianComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+/*-----------------PercentileComprehension------------------- */
+// percentile![p; mapping for ...], p的取值范围为0..=100
+#[derive(Debug)]
+pub struct PercentileComprehension {
+    pub percentile: Expr,
+    pub mapping: Mapping,
+    pub iter_clauses: Vec<IterClause>,
+}
+
+impl quote::ToTokens for PercentileComprehension {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let percentile = &self.percentile;
+        let nested_code = ok_or_emit!(
+            tokens,
+            percentile_tokens(
+                "percentile!",
+                quote! { #percentile },
+                &self.mapping,
+                &self.iter_clauses,
+            )
+        );
+        tokens.extend(nested_code);
+    }
+}
+
+impl syn::parse::Parse for PercentileComprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let percentile = input.parse::<Expr>()?;
+        input.parse::<Token![;]>()?;
+
+        let (mapping, iter_clauses) = crate::common_parse(input)?;
+
+        Ok(Self {
+            percentile,
+            mapping,
+            iter_clauses,
+        })
+    }
+}
@@ -0,0 +1,67 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Expr;
+use syn::parse::ParseStream;
+
+mod keyword {
+    syn::custom_keyword!(with);
+    syn::custom_keyword!(capacity);
+}
+
+/*-----------------CapacityClause------------------- */
+// with capacity n, 由调用者给出预分配的容量
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct CapacityClause {
+    pub capacity: Expr,
+}
+
+impl syn::parse::Parse for CapacityClause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<keyword::with>()?;
+        input.parse::<keyword::capacity>()?;
+        Ok(Self {
+            capacity: input.parse::<Expr>()?,
+        })
+    }
+}
+
+pub fn parse_capacity(input: ParseStream) -> syn::Result<Option<CapacityClause>> {
+    match input.peek(keyword::with) {
+        true => Ok(Some(input.parse::<CapacityClause>()?)),
+        false => Ok(None),
+    }
+}
+
+// 返回(创建集合的代码, 自动预分配容量的代码)
+// 给出了容量时使用with_capacity, 否则交给handle_nested_loops_with_reserve根据size_hint预分配
+pub fn handle_capacity(
+    capacity: Option<&CapacityClause>,
+    collection: TokenStream,
+    var: TokenStream,
+) -> (TokenStream, Option<TokenStream>) {
+    match capacity {
+        Some(CapacityClause { capacity }) => {
+            (quote! { #collection::with_capacity(#capacity) }, None)
+        }
+        None => (
+            quote! { #collection::new() },
+            Some(quote! { #var.reserve(__comprehension_capacity); }),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_capacity_parse() {
+        let capacity: CapacityClause = parse_quote! {
+            with capacity data.len() * 2
+        };
+        assert!(matches!(capacity.capacity, Expr::Binary(_)));
+        eprintln!("CapacityClause测试通过");
+    }
+}
@@ -0,0 +1,413 @@
+use syn::Expr;
+use syn::Token;
+use syn::parse::ParseStream;
+
+mod keyword {
+    syn::custom_keyword!(join);
+    syn::custom_keyword!(left);
+    syn::custom_keyword!(on);
+}
+
+/*-----------------IterClause------------------- */
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct IterClause {
+    pub for_in_clause: ForInClause,
+    pub join_clauses: Vec<JoinClause>,
+    pub if_clause: Option<BareIfClause>,
+    pub let_clauses: Vec<LetClause>,
+}
+
+impl syn::parse::Parse for IterClause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut iter_clause = Self {
+            for_in_clause: input.parse::<ForInClause>()?,
+            join_clauses: vec![],
+            if_clause: None,
+            let_clauses: vec![],
+        };
+
+        while input.peek(keyword::join) || (input.peek(keyword::left) && input.peek2(keyword::join))
+        {
+            iter_clause.join_clauses.push(input.parse::<JoinClause>()?);
+        }
+
+        if input.peek(syn::Token![if]) {
+            iter_clause.if_clause = Some(input.parse::<BareIfClause>()?);
+        }
+
+        while input.peek(syn::Token![let]) {
+            iter_clause.let_clauses.push(input.parse::<LetClause>()?);
+        }
+
+        Ok(iter_clause)
+    }
+}
+
+/*-----------------ForInClause------------------- */
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct ForInClause {
+    pub pat: syn::Pat,
+    pub iterable: Expr,
+    // `for x in? results` 对每个元素使用`?`解包
+    pub fallible: bool,
+}
+
+impl syn::parse::Parse for ForInClause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![for]>()?;
+        let pat = input.call(syn::Pat::parse_single)?;
+        input.parse::<Token![in]>()?;
+
+        let fallible = input.peek(Token![?]);
+        if fallible {
+            input.parse::<Token![?]>()?;
+        }
+
+        // `in ref mut collection` 以可变引用遍历, 展开为 `(collection).iter_mut()`
+        // 外层的括号使其不经过iter()的检查, 直接作为可迭代对象
+        if input.peek(Token![ref]) && input.peek2(Token![mut]) {
+            input.parse::<Token![ref]>()?;
+            input.parse::<Token![mut]>()?;
+            let collection = input.parse::<Expr>()?;
+            return Ok(Self {
+                pat,
+                iterable: syn::parse_quote! { ((#collection).iter_mut()) },
+                fallible,
+            });
+        }
+
+        Ok(Self {
+            pat,
+            iterable: input.parse::<Expr>()?,
+            fallible,
+        })
+    }
+}
+
+impl ForInClause {
+    // 模式绑定的变量, 以大写字母开头的标识符视为常量或枚举变体
+    pub fn bindings(&self) -> Vec<syn::Ident> {
+        pat_bindings(&self.pat)
+    }
+}
+
+fn pat_bindings(pat: &syn::Pat) -> Vec<syn::Ident> {
+    struct Finder(Vec<syn::Ident>);
+    impl<'ast> syn::visit::Visit<'ast> for Finder {
+        fn visit_pat_ident(&mut self, node: &'ast syn::PatIdent) {
+            if !node.ident.to_string().starts_with(char::is_uppercase) {
+                self.0.push(node.ident.clone());
+            }
+            syn::visit::visit_pat_ident(self, node);
+        }
+    }
+
+    let mut finder = Finder(vec![]);
+    syn::visit::Visit::visit_pat(&mut finder, pat);
+    finder.0
+}
+
+/*-----------------JoinClause------------------- */
+// [left] join pattern in iterable on outer_key == inner_key
+// 左侧为外层的键, 右侧为被连接的集合的键, 多个键用 && 连接或写成元组
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct JoinClause {
+    pub is_left: bool,
+    pub pat: syn::Pat,
+    pub iterable: Expr,
+    pub on: Expr,
+}
+
+impl syn::parse::Parse for JoinClause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut is_left = false;
+        if input.peek(keyword::left) {
+            input.parse::<keyword::left>()?;
+            is_left = true;
+        }
+        input.parse::<keyword::join>()?;
+        let pat = input.call(syn::Pat::parse_single)?;
+        input.parse::<Token![in]>()?;
+        let iterable = input.parse::<Expr>()?;
+        input.parse::<keyword::on>()?;
+
+        Ok(Self {
+            is_left,
+            pat,
+            iterable,
+            on: input.parse::<Expr>()?,
+        })
+    }
+}
+
+impl JoinClause {
+    // 将 `a == b && c == d` 拆分为 ((a, c), (b, d))
+    pub fn keys(&self) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+        fn split<'a>(
+            expr: &'a Expr,
+            outer: &mut Vec<&'a Expr>,
+            inner: &mut Vec<&'a Expr>,
+        ) -> syn::Result<()> {
+            match expr {
+                Expr::Binary(syn::ExprBinary {
+                    left,
+                    op: syn::BinOp::And(_),
+                    right,
+                    ..
+                }) => {
+                    split(left, outer, inner)?;
+                    split(right, outer, inner)?;
+                }
+                Expr::Binary(syn::ExprBinary {
+                    left,
+                    op: syn::BinOp::Eq(_),
+                    right,
+                    ..
+                }) => {
+                    outer.push(left);
+                    inner.push(right);
+                }
+                Expr::Paren(expr) => split(&expr.expr, outer, inner)?,
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        expr,
+                        "the condition of join must be `outer_key == inner_key`, \
+                        or several of them joined by `&&`",
+                    ));
+                }
+            }
+            Ok(())
+        }
+
+        let mut outer = vec![];
+        let mut inner = vec![];
+        split(&self.on, &mut outer, &mut inner)?;
+
+        Ok(match outer.len() {
+            1 => {
+                let (outer, inner) = (outer[0], inner[0]);
+                (quote::quote! { #outer }, quote::quote! { #inner })
+            }
+            _ => (
+                quote::quote! { (#(#outer),*) },
+                quote::quote! { (#(#inner),*) },
+            ),
+        })
+    }
+}
+
+/*-----------------BareIfClause------------------- */
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct BareIfClause {
+    pub conditions: Expr,
+}
+
+impl syn::parse::Parse for BareIfClause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![if]>()?;
+        Ok(Self {
+            conditions: input.parse::<Expr>()?,
+        })
+    }
+}
+
+/* ----------------LetClause-------------------- */
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct LetClause {
+    pub let_expr: Expr,
+}
+
+impl LetClause {
+    // `let` 模式绑定的变量, 不是 `let` 表达式时为空
+    pub fn bindings(&self) -> Vec<syn::Ident> {
+        match &self.let_expr {
+            Expr::Let(expr_let) => pat_bindings(&expr_let.pat),
+            _ => vec![],
+        }
+    }
+}
+
+impl syn::parse::Parse for LetClause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Ok(Self {
+            let_expr: input.parse::<Expr>()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_iter_clause_parse() {
+        // 测试基本的for-in子句解析
+        let iter_clause: IterClause = parse_quote! {
+            for x in vec![1, 2, 3]
+        };
+        assert!(matches!(iter_clause.for_in_clause.pat, syn::Pat::Ident(_)));
+        assert!(matches!(iter_clause.for_in_clause.iterable, Expr::Macro(_)));
+        assert!(iter_clause.if_clause.is_none());
+        eprintln!("IterClause基本for-in子句测试通过");
+
+        // 测试 `in ref mut` 展开为iter_mut
+        let iter_clause: IterClause = parse_quote! {
+            for y in ref mut *row if *y > 0
+        };
+        assert!(matches!(iter_clause.for_in_clause.iterable, Expr::Paren(_)));
+        let iterable = &iter_clause.for_in_clause.iterable;
+        assert_eq!(
+            quote::quote! { #iterable }.to_string(),
+            "((* row) . iter_mut ())"
+        );
+        assert!(iter_clause.if_clause.is_some());
+        eprintln!("IterClause ref mut 测试通过");
+
+        // 测试 `in?` 逐个解包
+        let iter_clause: IterClause = parse_quote! {
+            for x in? results if x > 0
+        };
+        assert!(iter_clause.for_in_clause.fallible);
+        assert!(matches!(iter_clause.for_in_clause.iterable, Expr::Path(_)));
+        assert!(iter_clause.if_clause.is_some());
+        eprintln!("IterClause in? 测试通过");
+
+        // 测试带if条件的for-in子句解析
+        let iter_clause: IterClause = parse_quote! {
+            for x in items if x > 0
+        };
+        assert!(matches!(iter_clause.for_in_clause.pat, syn::Pat::Ident(_)));
+        assert!(matches!(iter_clause.for_in_clause.iterable, Expr::Path(_)));
+        assert!(iter_clause.if_clause.is_some());
+        if let Some(if_clause) = &iter_clause.if_clause {
+            assert!(matches!(if_clause.conditions, Expr::Binary(_)));
+        }
+        eprintln!("IterClause带if条件的for-in子句测试通过");
+
+        // 测试复杂模式的for-in子句解析
+        let iter_clause: IterClause = parse_quote! {
+            for (x, y) in pairs
+        };
+        assert!(matches!(iter_clause.for_in_clause.pat, syn::Pat::Tuple(_)));
+        assert!(matches!(iter_clause.for_in_clause.iterable, Expr::Path(_)));
+        assert!(iter_clause.if_clause.is_none());
+        let bindings = iter_clause.for_in_clause.bindings();
+        assert_eq!(bindings.len(), 2);
+        assert_eq!(bindings[0], "x");
+        eprintln!("IterClause复杂模式的for-in子句测试通过");
+
+        // 测试复杂表达式的for-in子句解析
+        let iter_clause: IterClause = parse_quote! {
+            for x in vec.iter().map(|i| i * 2).filter(|i| i > 0) if x % 2 == 0
+        };
+        assert!(matches!(iter_clause.for_in_clause.pat, syn::Pat::Ident(_)));
+        assert!(matches!(
+            iter_clause.for_in_clause.iterable,
+            Expr::MethodCall(_)
+        ));
+        assert!(iter_clause.if_clause.is_some());
+        if let Some(if_clause) = &iter_clause.if_clause {
+            assert!(matches!(if_clause.conditions, Expr::Binary(_)));
+        }
+        eprintln!("IterClause复杂表达式的for-in子句测试通过");
+
+        // 测试带join的for-in子句解析
+        let iter_clause: IterClause = parse_quote! {
+            for a in xs join b in &ys on a.id == b.id left join c in zs.iter() on (a.x, b.y) == c.key if a.ok
+        };
+        assert_eq!(iter_clause.join_clauses.len(), 2);
+        assert!(!iter_clause.join_clauses[0].is_left);
+        assert!(iter_clause.join_clauses[1].is_left);
+        assert!(matches!(
+            iter_clause.join_clauses[1].iterable,
+            Expr::MethodCall(_)
+        ));
+        assert!(iter_clause.if_clause.is_some());
+        eprintln!("IterClause带join的for-in子句测试通过");
+    }
+
+    #[test]
+    fn test_join_clause_keys() {
+        // 单个键
+        let join: JoinClause = parse_quote! {
+            join b in ys on a.id == b.id
+        };
+        let (outer, inner) = join.keys().unwrap();
+        assert_eq!(outer.to_string(), "a . id");
+        assert_eq!(inner.to_string(), "b . id");
+        eprintln!("JoinClause单个键测试通过");
+
+        // 使用 && 连接的多个键
+        let join: JoinClause = parse_quote! {
+            join b in ys on a.x == b.x && a.y == b.y
+        };
+        let (outer, inner) = join.keys().unwrap();
+        assert_eq!(outer.to_string(), "(a . x , a . y)");
+        assert_eq!(inner.to_string(), "(b . x , b . y)");
+        eprintln!("JoinClause多个键测试通过");
+
+        // 不是 `==` 的条件
+        let join: JoinClause = parse_quote! {
+            join b in ys on a.id < b.id
+        };
+        assert!(join.keys().is_err());
+        eprintln!("JoinClause错误条件测试通过");
+    }
+
+    #[test]
+    fn test_for_in_clause_parse() {
+        // 测试基本的for-in子句解析
+        let for_in_clause: ForInClause = parse_quote! {
+            for x in items
+        };
+        assert!(matches!(for_in_clause.pat, syn::Pat::Ident(_)));
+        assert!(matches!(for_in_clause.iterable, Expr::Path(_)));
+        eprintln!("ForInClause基本解析测试通过");
+
+        // 测试复杂模式的for-in子句解析
+        let for_in_clause: ForInClause = parse_quote! {
+            for (a, b, c) in tuples
+        };
+        assert!(matches!(for_in_clause.pat, syn::Pat::Tuple(_)));
+        assert!(matches!(for_in_clause.iterable, Expr::Path(_)));
+        eprintln!("ForInClause复杂模式解析测试通过");
+
+        // 测试复杂表达式的for-in子句解析
+        let for_in_clause: ForInClause = parse_quote! {
+            for x in (0..10).filter(|n| n % 2 == 0)
+        };
+        assert!(matches!(for_in_clause.pat, syn::Pat::Ident(_)));
+        assert!(matches!(for_in_clause.iterable, Expr::MethodCall(_)));
+        eprintln!("ForInClause复杂表达式解析测试通过");
+    }
+
+    #[test]
+    fn test_bare_if_clause_parse() {
+        // 测试基本条件表达式解析
+        let if_clause: BareIfClause = parse_quote! {
+            if x > 0
+        };
+        assert!(matches!(if_clause.conditions, Expr::Binary(_)));
+        eprintln!("BareIfClause基本条件表达式测试通过");
+
+        // 测试复杂条件表达式解析
+        let if_clause: BareIfClause = parse_quote! {
+            if x > 0 && y < 10 || z == 5
+        };
+        assert!(matches!(if_clause.conditions, Expr::Binary(_)));
+        eprintln!("BareIfClause复杂条件表达式测试通过");
+
+        // 测试函数调用条件表达式解析
+        let if_clause: BareIfClause = parse_quote! {
+            if is_valid(x) && x.len() > 0
+        };
+        assert!(matches!(if_clause.conditions, Expr::Binary(_)));
+        eprintln!("BareIfClause函数调用条件表达式测试通过");
+    }
+}
@@ -0,0 +1,82 @@
+/*!
+Syntax tree, parser and loop lowering of the comprehension grammar used by
+[`better_comprehension`](https://crates.io/crates/better_comprehension).
+
+This is a normal library crate, so other procedural macros can accept the same grammar:
+
+* [`Comprehension`] parses `mapping for pat in iterable [join ...] [if ...] [let ...] ...`
+* [`mapping`] [`iter_clause`] [`sequence_clause`] [`capacity_clause`] contain the syntax tree
+* [`lowering`] turns the clauses into nested `for` loops or iterator pipelines
+
+```rust
+use better_comprehension_syntax::{Comprehension, lowering};
+use quote::quote;
+
+# fn main() -> syn::Result<()> {
+// the input of a hypothetical `sum_of![...]` macro
+let comprehension: Comprehension = syn::parse_str("x * 2 for x in 0..10 if x % 3 == 0")?;
+// errors point at the offending tokens, a macro emits them with `to_compile_error()`
+let item = comprehension.mapping.value_tokens("sum_of!")?;
+let loops = lowering::handle_nested_loops(
+    &comprehension.iter_clauses,
+    quote! { __sum += #item; },
+)?;
+let expanded = quote! {
+    {
+        let mut __sum = 0;
+        #loops
+        __sum
+    }
+};
+assert!(expanded.to_string().contains("for x in 0 .. 10"));
+# Ok(())
+# }
+```
+
+# Stability
+The crate follows semver. The syntax tree types are `#[non_exhaustive]`,
+so new clauses can be added in minor versions: match them with `..` and build them by parsing.
+Unsupported input is reported as a spanned [`syn::Error`], never as a panic.
+The tokens generated by [`lowering`] are only guaranteed to be equivalent,
+not identical, between versions.
+*/
+
+pub mod capacity_clause;
+pub mod iter_clause;
+pub mod lowering;
+pub mod mapping;
+pub mod sequence_clause;
+
+use syn::parse::ParseStream;
+
+/*-----------------Comprehension------------------- */
+// 所有推导式共用的部分: 映射与之后的所有iter_clause
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Comprehension {
+    pub mapping: mapping::Mapping,
+    pub iter_clauses: Vec<iter_clause::IterClause>,
+}
+
+impl syn::parse::Parse for Comprehension {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mapping = input.parse::<mapping::Mapping>()?;
+        let iter_clauses = parse_iter_clauses(input);
+
+        Ok(Self {
+            mapping,
+            iter_clauses,
+        })
+    }
+}
+
+// 解析尽可能多的iter_clause, 遇到无法解析的内容时停止(之后可以继续解析其他子句)
+pub fn parse_iter_clauses(input: ParseStream) -> Vec<iter_clause::IterClause> {
+    let mut iter_clauses = Vec::new();
+
+    while let Ok(iter_clause) = input.parse::<iter_clause::IterClause>() {
+        iter_clauses.push(iter_clause);
+    }
+
+    iter_clauses
+}
@@ -0,0 +1,740 @@
+use crate::capacity_clause::CapacityClause;
+use crate::iter_clause::{BareIfClause, ForInClause, IterClause, JoinClause, LetClause};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Expr;
+
+// 不支持的可迭代对象或join条件返回指向该表达式的错误
+pub fn handle_nested_loops(
+    iter_clauses: &[IterClause],
+    nested_code: TokenStream,
+) -> syn::Result<TokenStream> {
+    handle_nested_loops_with_reserve(iter_clauses, nested_code, None)
+}
+
+// 只有一层循环且没有if与join时, 产生的元素个数与迭代次数相同
+// 此时可以根据迭代器的size_hint预分配容量
+pub fn is_exact_size(iter_clauses: &[IterClause]) -> bool {
+    match iter_clauses {
+        [
+            IterClause {
+                join_clauses,
+                if_clause: None,
+                ..
+            },
+        ] => join_clauses.is_empty(),
+        _ => false,
+    }
+}
+
+// reserve 为预分配容量的代码, 其中可以使用 `__comprehension_capacity` (size_hint的下界)
+pub fn handle_nested_loops_with_reserve(
+    iter_clauses: &[IterClause],
+    nested_code: TokenStream,
+    reserve: Option<TokenStream>,
+) -> syn::Result<TokenStream> {
+    let reserve = reserve.filter(|_| is_exact_size(iter_clauses));
+    let (prelude, loops) = lower_loops(iter_clauses, nested_code, reserve, None, vec![])?;
+
+    Ok(quote! {
+        #prelude
+        #loops
+    })
+}
+
+// 并行展开时, 最外层的可迭代对象被分块交给各个线程
+// 返回(影子变量与join索引的声明, 最外层的可迭代对象, 遍历chunk的循环)
+// 声明只执行一次, 各个线程中的循环以引用的方式使用它们
+pub fn handle_parallel_loops(
+    iter_clauses: &[IterClause],
+    chunk: TokenStream,
+    nested_code: TokenStream,
+) -> syn::Result<(TokenStream, TokenStream, TokenStream)> {
+    let mut need_to_shadow: Vec<&Expr> = vec![];
+    let outer_iterable =
+        iterable_tokens(&iter_clauses[0].for_in_clause.iterable, &mut need_to_shadow)?;
+    let (prelude, loops) =
+        lower_loops(iter_clauses, nested_code, None, Some(chunk), need_to_shadow)?;
+
+    Ok((prelude, outer_iterable, loops))
+}
+
+// 返回(影子变量与join索引的声明, 循环)
+// outer_iterable 用于替换最外层的可迭代对象
+fn lower_loops<'a>(
+    iter_clauses: &'a [IterClause],
+    mut nested_code: TokenStream,
+    reserve: Option<TokenStream>,
+    outer_iterable: Option<TokenStream>,
+    mut need_to_shadow: Vec<&'a Expr>,
+) -> syn::Result<(TokenStream, TokenStream)> {
+    check_join_iterables(iter_clauses)?;
+    let mut outer_iterable = outer_iterable;
+    // join的索引只在最外层构建一次
+    let mut join_indexes: Vec<TokenStream> = vec![];
+
+    // 遍历iter_clauses(因为越向后层次越深, 所以直接pop就行了)
+    let mut iter_clauses: Vec<&IterClause> = iter_clauses.iter().collect();
+
+    while let Some(iter_clause) = iter_clauses.pop() {
+        let IterClause {
+            for_in_clause:
+                ForInClause {
+                    pat,
+                    iterable,
+                    fallible,
+                },
+            join_clauses,
+            if_clause,
+            let_clauses,
+        } = iter_clause;
+
+        // `?` 需要拥有所有权的Result, 因此 `in?` 按值遍历变量
+        let iterable_code = match (fallible, iterable) {
+            _ if iter_clauses.is_empty() && outer_iterable.is_some() => {
+                outer_iterable.take().unwrap()
+            }
+            (true, Expr::Path(_)) => quote! { #iterable },
+            _ => iterable_tokens(iterable, &mut need_to_shadow)?,
+        };
+
+        let mut let_clauses: Vec<&LetClause> = let_clauses.iter().collect();
+        while let Some(LetClause { let_expr }) = let_clauses.pop() {
+            nested_code = quote! {
+                #let_expr;
+                #nested_code
+            };
+        }
+
+        // if条件作用于join之后
+        if let Some(BareIfClause { conditions }) = if_clause {
+            nested_code = quote! {
+                if #conditions {
+                    #nested_code
+                }
+            };
+        }
+
+        for join_clause in join_clauses.iter().rev() {
+            let join_number = join_indexes.len();
+            let (index, code) =
+                handle_join(join_clause, join_number, &mut need_to_shadow, nested_code)?;
+            join_indexes.push(index);
+            nested_code = code;
+        }
+
+        // `in?` 先绑定元素本身, 再用`?`解包到模式中
+        let pat = match fallible {
+            true => {
+                nested_code = quote! {
+                    let #pat = __comprehension_item?;
+                    #nested_code
+                };
+                quote! { __comprehension_item }
+            }
+            false => quote! { #pat },
+        };
+
+        nested_code = match &reserve {
+            Some(reserve) => quote! {
+                let __comprehension_iter = ::std::iter::IntoIterator::into_iter(#iterable_code);
+                let __comprehension_capacity =
+                    ::std::iter::Iterator::size_hint(&__comprehension_iter).0;
+                #reserve
+                for #pat in __comprehension_iter {
+                    #nested_code
+                }
+            },
+            None => quote! {
+                for #pat in #iterable_code {
+                    #nested_code
+                }
+            },
+        };
+    }
+
+    // 为需要影子变量的变量添加声明, 之后为join构建索引
+    let shadows = need_to_shadow.iter().map(|shadowed| {
+        quote! { let #shadowed = #shadowed; }
+    });
+    let prelude = quote! {
+        #(#shadows)*
+        #(#join_indexes)*
+    };
+
+    Ok((prelude, nested_code))
+}
+
+// 对于只有for与if的简单形状, 生成 `into_iter().filter_map(..).collect()` 形式的迭代器管道
+// 这样可以利用collect对TrustedLen等的特化; 多层嵌套时使用flat_map
+// 无法保证与循环语义一致时返回None, 由调用者回退到push循环(不支持的可迭代对象由push循环报告错误)
+pub fn handle_pipeline(
+    iter_clauses: &[IterClause],
+    item: TokenStream,
+    collection: TokenStream,
+) -> Option<TokenStream> {
+    handle_pipeline_with(iter_clauses, item, |pipeline| {
+        quote! { ::std::iter::Iterator::collect::<#collection>(#pipeline) }
+    })
+}
+
+// 与handle_pipeline相同, 但由consume决定如何消费迭代器(如交给Extend::extend)
+// 迭代器可能借用影子变量, 因此必须在返回的块内被消费
+pub fn handle_pipeline_with(
+    iter_clauses: &[IterClause],
+    item: TokenStream,
+    consume: impl FnOnce(TokenStream) -> TokenStream,
+) -> Option<TokenStream> {
+    if iter_clauses.is_empty()
+        || iter_clauses.iter().any(|clause| {
+            !clause.let_clauses.is_empty()
+                || !clause.join_clauses.is_empty()
+                || clause.for_in_clause.fallible
+        })
+    {
+        return None;
+    }
+
+    // 第i层(从0开始)的闭包中包含: 第i层的if条件, 第i+1层的可迭代对象, 最内层时还有映射
+    // 除最外层外的闭包都需要move(以持有外层的模式变量), 因此其中只能引用已绑定的模式变量
+    let mut bound: Vec<String> = vec![];
+    // 外层中按值绑定(不能确定是Copy)的模式变量: 内层的可迭代对象会借用闭包拥有的值,
+    // 在更深的闭包中使用时需要从FnMut闭包中移出, 因此内层使用它们时回退到push循环
+    let mut owned: Vec<String> = vec![];
+    for (depth, iter_clause) in iter_clauses.iter().enumerate() {
+        let ForInClause { pat, iterable, .. } = &iter_clause.for_in_clause;
+        if uses_any(iterable, &owned) {
+            return None;
+        }
+
+        // 本层绑定的变量(包括if let绑定的变量)遮蔽外层的同名变量
+        let mut pat_bound: Vec<String> = vec![];
+        collect_bindings(pat, &mut pat_bound);
+        let mut if_bound: Vec<String> = vec![];
+        if let Some(BareIfClause { conditions }) = &iter_clause.if_clause {
+            let mut finder = BindingFinder {
+                bound: &mut if_bound,
+            };
+            finder.visit_expr(conditions);
+        }
+        owned.retain(|name| !pat_bound.contains(name) && !if_bound.contains(name));
+        if let Some(BareIfClause { conditions }) = &iter_clause.if_clause
+            && uses_any(conditions, &owned)
+        {
+            return None;
+        }
+        let is_innermost = depth + 1 == iter_clauses.len();
+        let item = match is_innermost {
+            true => match syn::parse2::<Expr>(item.clone()) {
+                Ok(item) => Some(item),
+                Err(_) => return None,
+            },
+            false => None,
+        };
+        if let Some(item) = &item
+            && uses_any(item, &owned)
+        {
+            return None;
+        }
+        // if let 绑定的变量总是视为按值绑定
+        if !yields_copy(iterable) {
+            owned.extend(pat_bound.iter().cloned());
+        }
+        owned.extend(if_bound.iter().cloned());
+        bound.extend(pat_bound);
+        bound.extend(if_bound);
+
+        let is_move = depth > 0;
+        let mut checker = ClosureChecker {
+            bound: &bound,
+            check_captures: is_move,
+            is_safe: true,
+        };
+        if let Some(BareIfClause { conditions }) = &iter_clause.if_clause {
+            checker.visit_expr(conditions);
+        }
+        match (iter_clauses.get(depth + 1), &item) {
+            (Some(next), _) => checker.visit_expr(&next.for_in_clause.iterable),
+            (None, Some(item)) => checker.visit_expr(item),
+            (None, None) => unreachable!(),
+        }
+        if !checker.is_safe {
+            return None;
+        }
+    }
+
+    let mut need_to_shadow: Vec<&Expr> = vec![];
+    let mut iterables: Vec<TokenStream> = iter_clauses
+        .iter()
+        .map(|iter_clause| {
+            iterable_tokens(&iter_clause.for_in_clause.iterable, &mut need_to_shadow)
+        })
+        .collect::<syn::Result<_>>()
+        .ok()?;
+
+    // 从最内层开始向外构建
+    let mut pipeline = item;
+    let mut is_innermost = true;
+    for (depth, iter_clause) in iter_clauses.iter().enumerate().rev() {
+        let IterClause {
+            for_in_clause: ForInClause { pat, .. },
+            if_clause,
+            ..
+        } = iter_clause;
+        let iterable_code = iterables.pop().unwrap();
+        let move_token = match depth > 0 {
+            true => quote! { move },
+            false => quote! {},
+        };
+
+        pipeline = match (if_clause, is_innermost) {
+            (None, true) => quote! {
+                ::std::iter::Iterator::map(
+                    ::std::iter::IntoIterator::into_iter(#iterable_code),
+                    #move_token |#pat| #pipeline,
+                )
+            },
+            (None, false) => quote! {
+                ::std::iter::Iterator::flat_map(
+                    ::std::iter::IntoIterator::into_iter(#iterable_code),
+                    #move_token |#pat| #pipeline,
+                )
+            },
+            // if中可能有 `if let` 绑定, 因此条件与映射放在同一个闭包中
+            (Some(BareIfClause { conditions }), true) => quote! {
+                ::std::iter::Iterator::filter_map(
+                    ::std::iter::IntoIterator::into_iter(#iterable_code),
+                    #move_token |#pat| if #conditions {
+                        ::std::option::Option::Some(#pipeline)
+                    } else {
+                        ::std::option::Option::None
+                    },
+                )
+            },
+            (Some(BareIfClause { conditions }), false) => quote! {
+                ::std::iter::Iterator::flatten(::std::iter::Iterator::filter_map(
+                    ::std::iter::IntoIterator::into_iter(#iterable_code),
+                    #move_token |#pat| if #conditions {
+                        ::std::option::Option::Some(#pipeline)
+                    } else {
+                        ::std::option::Option::None
+                    },
+                ))
+            },
+        };
+        is_innermost = false;
+    }
+
+    let mut nested_code = consume(pipeline);
+    while let Some(shadowed) = need_to_shadow.pop() {
+        nested_code = quote! {
+            let #shadowed = #shadowed;
+            #nested_code
+        };
+    }
+
+    Some(quote! {
+        {
+            #nested_code
+        }
+    })
+}
+
+// 将所有元素缓存到Vec中, 用于之后批量构建集合
+// 简单的形状使用迭代器管道, 否则使用push循环
+pub fn handle_buffer(
+    iter_clauses: &[IterClause],
+    item: TokenStream,
+    capacity: Option<&CapacityClause>,
+) -> syn::Result<TokenStream> {
+    if capacity.is_none()
+        && let Some(pipeline) =
+            handle_pipeline(iter_clauses, item.clone(), quote! { ::std::vec::Vec<_> })
+    {
+        return Ok(pipeline);
+    }
+
+    let (new_collection, reserve) = crate::capacity_clause::handle_capacity(
+        capacity,
+        quote! { ::std::vec::Vec },
+        quote! { __comprehension_buffer },
+    );
+    let nested_code = handle_nested_loops_with_reserve(
+        iter_clauses,
+        quote! { __comprehension_buffer.push(#item); },
+        reserve,
+    )?;
+    Ok(quote! {
+        {
+            let mut __comprehension_buffer = #new_collection;
+            #nested_code
+            __comprehension_buffer
+        }
+    })
+}
+
+// 可迭代对象的元素是否一定是Copy的: 引用(按引用遍历的变量, 引用表达式, iter()及不改变元素的适配器)或范围的元素
+fn yields_copy(iterable: &Expr) -> bool {
+    match iterable {
+        Expr::Path(_) | Expr::Reference(_) | Expr::Range(_) => true,
+        Expr::Paren(expr) => yields_copy(&expr.expr),
+        Expr::MethodCall(method_call) => {
+            let method = method_call.method.to_string();
+            match method.as_str() {
+                "iter" => method_call.args.is_empty(),
+                "enumerate" | "filter" | "skip" | "take" | "rev" | "step_by" | "skip_while"
+                | "take_while" => yields_copy(&method_call.receiver),
+                _ => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+// 表达式中是否使用了给定的变量, 无法解析的宏视为使用了
+fn uses_any(expr: &Expr, names: &[String]) -> bool {
+    struct UseFinder<'a> {
+        names: &'a [String],
+        found: bool,
+    }
+    impl<'ast> Visit<'ast> for UseFinder<'_> {
+        fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+            if let Some(ident) = node.path.get_ident()
+                && self.names.contains(&ident.to_string())
+            {
+                self.found = true;
+            }
+        }
+        fn visit_macro(&mut self, node: &'ast syn::Macro) {
+            match node.parse_body_with(
+                syn::punctuated::Punctuated::<Expr, syn::Token![,]>::parse_terminated,
+            ) {
+                Ok(exprs) => exprs.iter().for_each(|expr| self.visit_expr(expr)),
+                Err(_) => self.found = true,
+            }
+        }
+    }
+
+    if names.is_empty() {
+        return false;
+    }
+    let mut finder = UseFinder {
+        names,
+        found: false,
+    };
+    finder.visit_expr(expr);
+    finder.found
+}
+
+// join的索引在所有循环之前构建, 被连接的集合不能使用外层子句绑定的变量
+fn check_join_iterables(iter_clauses: &[IterClause]) -> syn::Result<()> {
+    let mut bound: Vec<String> = vec![];
+    for iter_clause in iter_clauses {
+        collect_bindings(&iter_clause.for_in_clause.pat, &mut bound);
+        for join_clause in &iter_clause.join_clauses {
+            if uses_any(&join_clause.iterable, &bound) {
+                return Err(syn::Error::new_spanned(
+                    &join_clause.iterable,
+                    "the joined iterable is evaluated once before all the loops, \
+                     it cannot use the variables bound by outer clauses",
+                ));
+            }
+            collect_bindings(&join_clause.pat, &mut bound);
+        }
+        let mut finder = BindingFinder { bound: &mut bound };
+        if let Some(BareIfClause { conditions }) = &iter_clause.if_clause {
+            finder.visit_expr(conditions);
+        }
+        for LetClause { let_expr } in &iter_clause.let_clauses {
+            finder.visit_expr(let_expr);
+        }
+    }
+    Ok(())
+}
+
+fn collect_bindings(pat: &syn::Pat, bound: &mut Vec<String>) {
+    let mut finder = BindingFinder { bound };
+    finder.visit_pat(pat);
+}
+
+// 收集模式(包括if条件中的 `let` 模式)绑定的变量名
+struct BindingFinder<'a> {
+    bound: &'a mut Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for BindingFinder<'_> {
+    fn visit_pat_ident(&mut self, node: &'ast syn::PatIdent) {
+        self.bound.push(node.ident.to_string());
+        syn::visit::visit_pat_ident(self, node);
+    }
+
+    fn visit_expr_let(&mut self, node: &'ast syn::ExprLet) {
+        self.visit_pat(&node.pat);
+    }
+
+    // 闭包与块中的绑定只在其内部有效
+    fn visit_expr_closure(&mut self, _: &'ast syn::ExprClosure) {}
+    fn visit_block(&mut self, _: &'ast syn::Block) {}
+}
+
+// 检查一段代码放入闭包后语义是否不变
+// return/break/continue/?/await 在闭包中含义不同
+// move闭包中引用外部的局部变量会将其移动进闭包, 因此只允许引用模式变量
+// (以大写字母开头的路径视为类型, 常量或枚举变体; move闭包中的宏可能隐式捕获变量, 一律视为不安全)
+struct ClosureChecker<'a> {
+    bound: &'a [String],
+    check_captures: bool,
+    is_safe: bool,
+}
+
+impl<'ast> Visit<'ast> for ClosureChecker<'_> {
+    fn visit_expr_return(&mut self, _: &'ast syn::ExprReturn) {
+        self.is_safe = false;
+    }
+    fn visit_expr_break(&mut self, _: &'ast syn::ExprBreak) {
+        self.is_safe = false;
+    }
+    fn visit_expr_continue(&mut self, _: &'ast syn::ExprContinue) {
+        self.is_safe = false;
+    }
+    fn visit_expr_try(&mut self, _: &'ast syn::ExprTry) {
+        self.is_safe = false;
+    }
+    fn visit_expr_await(&mut self, _: &'ast syn::ExprAwait) {
+        self.is_safe = false;
+    }
+    fn visit_expr_yield(&mut self, _: &'ast syn::ExprYield) {
+        self.is_safe = false;
+    }
+
+    fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+        if self.check_captures
+            && node.qself.is_none()
+            && let Some(ident) = node.path.get_ident()
+        {
+            let name = ident.to_string();
+            if !name.starts_with(char::is_uppercase) && !self.bound.contains(&name) {
+                self.is_safe = false;
+            }
+        }
+        syn::visit::visit_expr_path(self, node);
+    }
+
+    // 宏的内容按逗号分隔的表达式分析(如 format!, vec!), 无法解析时视为不安全
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        if self.check_captures {
+            self.is_safe = false;
+            return;
+        }
+        match node
+            .parse_body_with(syn::punctuated::Punctuated::<Expr, syn::Token![,]>::parse_terminated)
+        {
+            Ok(exprs) => exprs.iter().for_each(|expr| self.visit_expr(expr)),
+            Err(_) => self.is_safe = false,
+        }
+    }
+}
+
+fn iterable_tokens<'a>(
+    iterable: &'a Expr,
+    need_to_shadow: &mut Vec<&'a Expr>,
+) -> syn::Result<TokenStream> {
+    match iterable {
+        // `.await` 的结果按值遍历
+        Expr::Reference(_) | Expr::Range(_) | Expr::Await(_) => Ok(quote! { #iterable }),
+        Expr::Path(_) => {
+            need_to_shadow.push(iterable);
+            Ok(quote! { &#iterable })
+        }
+        Expr::MethodCall(_) => match is_iter(iterable) {
+            true => Ok(quote! { #iterable }),
+            false => Err(syn::Error::new_spanned(
+                iterable,
+                "please ensure the first method call is iter(), \
+                or wrap the iterable in parentheses",
+            )),
+        },
+        Expr::Paren(expr) => {
+            let iterable = &*expr.expr;
+            Ok(quote! { #iterable })
+        }
+        _ => Err(syn::Error::new_spanned(
+            iterable,
+            "this iterable is not supported, wrap it in parentheses to iterate it by value",
+        )),
+    }
+}
+
+// 返回(构建索引的代码, 查找索引的循环代码)
+// 被连接的集合按键分组为 HashMap<键, Vec<元素>>, 每个外层元素只需查找一次
+fn handle_join<'a>(
+    join_clause: &'a JoinClause,
+    join_number: usize,
+    need_to_shadow: &mut Vec<&'a Expr>,
+    nested_code: TokenStream,
+) -> syn::Result<(TokenStream, TokenStream)> {
+    let JoinClause {
+        is_left,
+        pat,
+        iterable,
+        ..
+    } = join_clause;
+    let (outer_key, inner_key) = join_clause.keys()?;
+    let iterable_code = iterable_tokens(iterable, need_to_shadow)?;
+    let index = quote::format_ident!("__join_index_{}", join_number);
+
+    let index_code = quote! {
+        let mut #index: ::std::collections::HashMap<_, ::std::vec::Vec<_>> =
+            ::std::collections::HashMap::new();
+        for __join_item in #iterable_code {
+            let __join_key = {
+                let #pat = ::std::clone::Clone::clone(&__join_item);
+                #inner_key
+            };
+            #index.entry(__join_key).or_default().push(__join_item);
+        }
+    };
+
+    // left join 时, 没有匹配的外层元素绑定为None
+    let loop_code = match is_left {
+        false => quote! {
+            for #pat in #index.get(&(#outer_key)).into_iter().flatten().cloned() {
+                #nested_code
+            }
+        },
+        true => quote! {
+            let __join_bucket = #index.get(&(#outer_key));
+            for #pat in __join_bucket
+                .into_iter()
+                .flatten()
+                .cloned()
+                .map(::std::option::Option::Some)
+                .chain(__join_bucket.is_none().then_some(::std::option::Option::None))
+            {
+                #nested_code
+            }
+        },
+    };
+
+    Ok((index_code, loop_code))
+}
+
+struct IterMethodCallFinder {
+    is_iter: bool,
+}
+
+use syn::{ExprMethodCall, visit::Visit};
+impl<'ast> Visit<'ast> for IterMethodCallFinder {
+    fn visit_expr_method_call(&mut self, node: &'ast ExprMethodCall) {
+        match *node.receiver {
+            syn::Expr::Path(_) | syn::Expr::Field(_) => {
+                if node.method == "iter" {
+                    self.is_iter = true;
+                }
+            }
+            _ => syn::visit::visit_expr(&mut *self, &node.receiver),
+        }
+    }
+}
+
+fn is_iter(expr: &syn::Expr) -> bool {
+    let mut finder = IterMethodCallFinder { is_iter: false };
+    finder.visit_expr(expr);
+
+    finder.is_iter
+}
+
+#[test]
+fn test_is_iter() {
+    // 最右侧是iter方法
+    let expr = syn::parse_quote!(some.method_1().method_2().iter());
+    assert!(!is_iter(&expr));
+    eprintln!("--------------------------------");
+    // 最左侧是iter方法
+    let expr = syn::parse_quote!(some.iter().method_3().method_4());
+    assert!(is_iter(&expr));
+    eprintln!("--------------------------------");
+
+    let expr = syn::parse_quote!(some.method_5().iter().method_6());
+    assert!(!is_iter(&expr));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Comprehension;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_pipeline_shapes() {
+        let pipeline = |comprehension: Comprehension| {
+            handle_pipeline(
+                &comprehension.iter_clauses,
+                comprehension.mapping.value_tokens("Vec").unwrap(),
+                quote! { Vec<_> },
+            )
+        };
+
+        // 可以生成迭代器管道的形状
+        assert!(pipeline(parse_quote! { x * 2 for x in items }).is_some());
+        assert!(pipeline(parse_quote! { x for x in items.iter() if x > 0 }).is_some());
+        assert!(pipeline(parse_quote! { y for x in outer if let Some(y) = x }).is_some());
+        assert!(pipeline(parse_quote! { (x, y) for x in outer for y in x.iter() }).is_some());
+        assert!(pipeline(parse_quote! { format!("{}", x) for x in items }).is_some());
+        eprintln!("迭代器管道形状测试通过");
+
+        // 需要回退到push循环的形状
+        assert!(pipeline(parse_quote! { y for x in items let y = x + 1 }).is_none());
+        assert!(pipeline(parse_quote! { x? for x in items }).is_none());
+        assert!(pipeline(parse_quote! { x + n for x in items for _ in 0..2 }).is_none());
+        assert!(
+            pipeline(parse_quote! { format!("{}", x) for x in outer for y in inner }).is_none()
+        );
+        assert!(
+            pipeline(parse_quote! { x for x in outer for y in inner join z in other on y == z })
+                .is_none()
+        );
+        // 内层借用或移动外层按值绑定的变量
+        assert!(pipeline(parse_quote! { c for s in (make()) for c in (s.chars()) }).is_none());
+        assert!(
+            pipeline(parse_quote! { s.clone() for s in (make()) for i in 0..2 for j in 0..2 })
+                .is_none()
+        );
+        assert!(pipeline(parse_quote! { (i, j) for i in 0..3 for j in 0..i }).is_some());
+        eprintln!("回退到push循环的形状测试通过");
+    }
+
+    #[test]
+    fn test_unsupported_iterable() {
+        let loops = |comprehension: Comprehension| {
+            handle_nested_loops(&comprehension.iter_clauses, quote! {})
+        };
+
+        assert!(loops(parse_quote! { x for x in items.iter() }).is_ok());
+        assert!(loops(parse_quote! { x for x in (items.into_iter()) }).is_ok());
+
+        // 错误指向不支持的可迭代对象, 而不是panic
+        let error = loops(parse_quote! { x for x in items.into_iter() }).unwrap_err();
+        assert!(error.to_string().contains("iter()"));
+        let error = loops(parse_quote! { x for x in make() }).unwrap_err();
+        assert!(error.to_string().contains("not supported"));
+        let error = loops(parse_quote! { x for x in xs join y in ys on x < y }).unwrap_err();
+        assert!(error.to_string().contains("join"));
+        eprintln!("不支持的可迭代对象测试通过");
+
+        // 被连接的集合使用外层绑定的变量时报告错误
+        assert!(loops(parse_quote! { (x, y) for x in xs join y in ys on x.id == y.id }).is_ok());
+        let error = loops(parse_quote! {
+            (x, z) for x in xs for y in ys join z in (x.children.iter()) on y.id == z.id
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("outer clauses"));
+        let error = loops(parse_quote! {
+            (y, z) for x in xs let kids = &x.kids for y in ys join z in kids on y.id == z.id
+        })
+        .unwrap_err();
+        assert!(error.to_string().contains("outer clauses"));
+        eprintln!("join使用外层变量测试通过");
+    }
+}
@@ -7,6 +7,7 @@ use syn::parse_quote;
 
 /*-----------------Mapping------------------- */
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct Mapping {
     pub left_key: Expr,
     pub left_value: Option<Expr>,
@@ -43,9 +44,86 @@ impl syn::parse::Parse for Mapping {
     }
 }
 
+impl Mapping {
+    // 将非键值对的映射展开为单个表达式, 用于不区分键值的推导式
+    // 映射是键值对时, 错误指向多余的值
+    pub fn value_tokens(&self, name: &str) -> syn::Result<proc_macro2::TokenStream> {
+        let Mapping {
+            left_key,
+            left_value,
+            right_expr,
+        } = self;
+
+        let message = format!("{} doesn't accept key-value mapping", name);
+        if let Some(left_value) = left_value {
+            return Err(syn::Error::new_spanned(left_value, message));
+        }
+
+        match right_expr {
+            None => Ok(quote::quote! { #left_key }),
+            Some(MappingElse {
+                conditions,
+                else_key,
+                else_value,
+            }) => {
+                if let Some(else_value) = else_value {
+                    return Err(syn::Error::new_spanned(else_value, message));
+                }
+
+                Ok(quote::quote! {
+                    if #conditions {
+                        #left_key
+                    } else {
+                        #else_key
+                    }
+                })
+            }
+        }
+    }
+}
+
+impl Mapping {
+    // 将键值对的映射展开为 (键, 值) 元组, 用于迭代器管道
+    // 缺少值时, 错误指向只有键的表达式
+    pub fn pair_tokens(&self) -> syn::Result<proc_macro2::TokenStream> {
+        let Mapping {
+            left_key,
+            left_value,
+            right_expr,
+        } = self;
+
+        let message = "expected a key-value mapping `key => value`";
+        let Some(left_value) = left_value else {
+            return Err(syn::Error::new_spanned(left_key, message));
+        };
+
+        match right_expr {
+            None => Ok(quote::quote! { (#left_key, #left_value) }),
+            Some(MappingElse {
+                conditions,
+                else_key,
+                else_value,
+            }) => {
+                let Some(else_value) = else_value else {
+                    return Err(syn::Error::new_spanned(else_key, message));
+                };
+
+                Ok(quote::quote! {
+                    if #conditions {
+                        (#left_key, #left_value)
+                    } else {
+                        (#else_key, #else_value)
+                    }
+                })
+            }
+        }
+    }
+}
+
 /*-----------------MappingElse------------------- */
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub struct MappingElse {
     pub conditions: Expr,
     pub else_key: Expr,
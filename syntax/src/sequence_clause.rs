@@ -0,0 +1,341 @@
+use crate::iter_clause::IterClause;
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::Expr;
+use syn::Token;
+use syn::parse::ParseStream;
+
+mod keyword {
+    syn::custom_keyword!(order);
+    syn::custom_keyword!(by);
+    syn::custom_keyword!(asc);
+    syn::custom_keyword!(desc);
+    syn::custom_keyword!(take);
+    syn::custom_keyword!(distinct);
+}
+
+/*-----------------DistinctClause------------------- */
+// distinct [by key], 保留每个键第一次出现的元素
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct DistinctClause {
+    pub key: Option<Expr>,
+}
+
+impl syn::parse::Parse for DistinctClause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<keyword::distinct>()?;
+
+        let mut key = None;
+        if input.peek(keyword::by) {
+            input.parse::<keyword::by>()?;
+            key = Some(input.parse::<Expr>()?);
+        }
+
+        Ok(Self { key })
+    }
+}
+
+impl DistinctClause {
+    // 返回(去重后使用的值, 求值的前置代码, 键)
+    // 没有指定键时, 以映射本身作为键, 因此需要映射实现Clone
+    fn split(&self, value: TokenStream) -> (TokenStream, TokenStream, TokenStream) {
+        match &self.key {
+            Some(key) => (value, quote! {}, quote! { #key }),
+            None => (
+                quote! { __distinct_item },
+                quote! { let __distinct_item = #value; },
+                quote! { ::std::clone::Clone::clone(&__distinct_item) },
+            ),
+        }
+    }
+}
+
+pub fn parse_distinct(input: ParseStream) -> syn::Result<Option<DistinctClause>> {
+    match input.peek(keyword::distinct) {
+        true => Ok(Some(input.parse::<DistinctClause>()?)),
+        false => Ok(None),
+    }
+}
+
+/*-----------------OrderByClause------------------- */
+// order by key_1 [asc|desc], key_2 [asc|desc], ...
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct OrderByClause {
+    pub keys: Vec<OrderKey>,
+}
+
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct OrderKey {
+    pub key: Expr,
+    pub descending: bool,
+}
+
+impl syn::parse::Parse for OrderByClause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<keyword::order>()?;
+        input.parse::<keyword::by>()?;
+
+        let mut keys = vec![];
+        loop {
+            let key = input.parse::<Expr>()?;
+            let mut descending = false;
+            if input.peek(keyword::desc) {
+                input.parse::<keyword::desc>()?;
+                descending = true;
+            } else if input.peek(keyword::asc) {
+                input.parse::<keyword::asc>()?;
+            }
+            keys.push(OrderKey { key, descending });
+
+            if !input.peek(Token![,]) {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(Self { keys })
+    }
+}
+
+impl OrderByClause {
+    // 降序的键使用Reverse包装, 这样整个元组可以直接按Ord比较
+    fn key_tuple(&self) -> TokenStream {
+        let keys = self
+            .keys
+            .iter()
+            .map(|OrderKey { key, descending }| match descending {
+                true => quote! { ::std::cmp::Reverse(#key) },
+                false => quote! { #key },
+            });
+
+        quote! { ( #(#keys,)* ) }
+    }
+}
+
+/*-----------------TakeClause------------------- */
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct TakeClause {
+    pub count: Expr,
+}
+
+impl syn::parse::Parse for TakeClause {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<keyword::take>()?;
+        Ok(Self {
+            count: input.parse::<Expr>()?,
+        })
+    }
+}
+
+// 解析位于所有iter_clause之后的 `order by ...` 与 `take n`
+pub fn parse_order_by_take(
+    input: ParseStream,
+) -> syn::Result<(Option<OrderByClause>, Option<TakeClause>)> {
+    let mut order_by = None;
+    let mut take = None;
+
+    if input.peek(keyword::order) {
+        order_by = Some(input.parse::<OrderByClause>()?);
+    }
+    if input.peek(keyword::take) {
+        take = Some(input.parse::<TakeClause>()?);
+    }
+
+    Ok((order_by, take))
+}
+
+// 生成带 distinct / order by / take 的循环代码
+// push 负责生成把一个元素放入目标集合的代码
+pub fn handle_sequence_clauses(
+    iter_clauses: &[IterClause],
+    distinct: Option<&DistinctClause>,
+    order_by: Option<&OrderByClause>,
+    take: Option<&TakeClause>,
+    reserve: Option<TokenStream>,
+    value: TokenStream,
+    push: impl Fn(TokenStream) -> TokenStream,
+) -> syn::Result<TokenStream> {
+    // 去重会减少元素个数, 因此不能根据size_hint预分配; 有take时不超过n
+    let reserve = match (distinct, take, reserve) {
+        (None, None, reserve) => reserve,
+        (None, Some(_), Some(reserve)) => Some(quote! {
+            let __comprehension_capacity =
+                ::std::cmp::Ord::min(__comprehension_capacity, __take_comprehension);
+            #reserve
+        }),
+        _ => None,
+    };
+
+    // 去重发生在排序与截取之前, 以迭代顺序中第一次出现的元素为准
+    let (value, distinct_prelude, distinct_key) = match distinct {
+        Some(distinct) => {
+            let (value, prelude, key) = distinct.split(value);
+            (value, prelude, Some(key))
+        }
+        None => (value, quote! {}, None),
+    };
+    let handle_nested_loops = |inner_code: TokenStream| {
+        let inner_code = match &distinct_key {
+            Some(key) => quote! {
+                #distinct_prelude
+                if __distinct_seen.insert(#key) {
+                    #inner_code
+                }
+            },
+            None => inner_code,
+        };
+        crate::lowering::handle_nested_loops_with_reserve(iter_clauses, inner_code, reserve.clone())
+    };
+
+    let nested_code = match (order_by, take) {
+        (None, None) => {
+            let push_value = push(value);
+            handle_nested_loops(push_value)?
+        }
+        // 只有take时, 取够n个元素后直接跳出所有循环
+        (None, Some(TakeClause { count })) => {
+            let label = syn::Lifetime::new("'__take_comprehension", Span::mixed_site());
+            let push_value = push(value);
+            let nested_code = handle_nested_loops(quote! {
+                #push_value
+                __take_comprehension_count += 1;
+                if __take_comprehension_count >= __take_comprehension {
+                    break #label;
+                }
+            })?;
+
+            quote! {
+                let __take_comprehension: usize = #count;
+                if __take_comprehension > 0 {
+                    let mut __take_comprehension_count: usize = 0;
+                    #label: {
+                        #nested_code
+                    }
+                }
+            }
+        }
+        // 只有order by时, 先缓存(键, 值), 稳定排序后再放入目标集合
+        (Some(order_by), None) => {
+            let keys = order_by.key_tuple();
+            let push_item = push(quote! { __order_by_item });
+            let nested_code = handle_nested_loops(quote! {
+                __order_by_buffer.push((#keys, #value));
+            })?;
+
+            quote! {
+                let mut __order_by_buffer = ::std::vec::Vec::new();
+                #nested_code
+                __order_by_buffer.sort_by(|__a, __b| __a.0.cmp(&__b.0));
+                for (_, __order_by_item) in __order_by_buffer {
+                    #push_item
+                }
+            }
+        }
+        // order by 与 take 同时存在时, 使用大小为n的最大堆保留最小的n个元素, O(n log k)
+        // 堆中的元素附带出现顺序的下标, 以保证结果与稳定排序一致
+        (Some(order_by), Some(TakeClause { count })) => {
+            let keys = order_by.key_tuple();
+            let push_item = push(quote! { __order_by_item });
+            let nested_code = handle_nested_loops(quote! {
+                let __order_by_entry = __OrderByEntry(#keys, __order_by_index, #value);
+                __order_by_index += 1;
+                if __order_by_heap.len() < __take_comprehension {
+                    __order_by_heap.push(__order_by_entry);
+                } else if let ::std::option::Option::Some(mut __order_by_top) =
+                    __order_by_heap.peek_mut()
+                {
+                    if __order_by_entry < *__order_by_top {
+                        *__order_by_top = __order_by_entry;
+                    }
+                }
+            })?;
+
+            quote! {
+                struct __OrderByEntry<K, V>(K, usize, V);
+                impl<K: ::std::cmp::Ord, V> ::std::cmp::PartialEq for __OrderByEntry<K, V> {
+                    fn eq(&self, other: &Self) -> bool {
+                        self.cmp(other) == ::std::cmp::Ordering::Equal
+                    }
+                }
+                impl<K: ::std::cmp::Ord, V> ::std::cmp::Eq for __OrderByEntry<K, V> {}
+                impl<K: ::std::cmp::Ord, V> ::std::cmp::PartialOrd for __OrderByEntry<K, V> {
+                    fn partial_cmp(&self, other: &Self) -> ::std::option::Option<::std::cmp::Ordering> {
+                        ::std::option::Option::Some(self.cmp(other))
+                    }
+                }
+                impl<K: ::std::cmp::Ord, V> ::std::cmp::Ord for __OrderByEntry<K, V> {
+                    fn cmp(&self, other: &Self) -> ::std::cmp::Ordering {
+                        (&self.0, self.1).cmp(&(&other.0, other.1))
+                    }
+                }
+
+                let __take_comprehension: usize = #count;
+                let mut __order_by_heap = ::std::collections::BinaryHeap::new();
+                let mut __order_by_index: usize = 0;
+                if __take_comprehension > 0 {
+                    #nested_code
+                }
+                for __OrderByEntry(_, _, __order_by_item) in __order_by_heap.into_sorted_vec() {
+                    #push_item
+                }
+            }
+        }
+    };
+
+    Ok(match distinct {
+        Some(_) => quote! {
+            let mut __distinct_seen = ::std::collections::HashSet::new();
+            #nested_code
+        },
+        None => nested_code,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_order_by_parse() {
+        // 测试单个键
+        let order_by: OrderByClause = parse_quote! {
+            order by x.age
+        };
+        assert_eq!(order_by.keys.len(), 1);
+        assert!(!order_by.keys[0].descending);
+        eprintln!("OrderByClause单个键测试通过");
+
+        // 测试多个键与排序方向
+        let order_by: OrderByClause = parse_quote! {
+            order by x.age desc, x.name.len() asc, -x.id
+        };
+        assert_eq!(order_by.keys.len(), 3);
+        assert!(order_by.keys[0].descending);
+        assert!(!order_by.keys[1].descending);
+        assert!(matches!(order_by.keys[2].key, Expr::Unary(_)));
+        eprintln!("OrderByClause多个键测试通过");
+
+        // 测试take
+        let take: TakeClause = parse_quote! {
+            take n + 1
+        };
+        assert!(matches!(take.count, Expr::Binary(_)));
+        eprintln!("TakeClause测试通过");
+
+        // 测试distinct
+        let distinct: DistinctClause = parse_quote! {
+            distinct
+        };
+        assert!(distinct.key.is_none());
+        let distinct: DistinctClause = parse_quote! {
+            distinct by (x.id, x.name.clone())
+        };
+        assert!(matches!(distinct.key, Some(Expr::Tuple(_))));
+        eprintln!("DistinctClause测试通过");
+    }
+}